@@ -55,8 +55,8 @@ fn main() -> Result<()> {
             let ev = ev_ctx.wait_event(600.).unwrap_or(Err(Error::Null));
 
             match ev {
-                Ok(Event::EndFile(r)) => {
-                    println!("Exiting! Reason: {:?}", r);
+                Ok(Event::EndFile { reason, .. }) => {
+                    println!("Exiting! Reason: {:?}", reason);
                     break;
                 }
 
@@ -77,13 +77,13 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn seekable_ranges(demuxer_cache_state: &MpvNode) -> Option<Vec<(f64, f64)>> {
+fn seekable_ranges(demuxer_cache_state: MpvNodeRef) -> Option<Vec<(f64, f64)>> {
     let mut res = Vec::new();
-    let props: HashMap<&str, MpvNode> = demuxer_cache_state.to_map()?.collect();
+    let props: HashMap<&str, MpvNodeRef> = demuxer_cache_state.to_map()?.collect();
     let ranges = props.get("seekable-ranges")?.to_array()?;
 
     for node in ranges {
-        let range: HashMap<&str, MpvNode> = node.to_map()?.collect();
+        let range: HashMap<&str, MpvNodeRef> = node.to_map()?.collect();
         let start = range.get("start")?.to_f64()?;
         let end = range.get("end")?.to_f64()?;
         res.push((start, end));
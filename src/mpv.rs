@@ -16,7 +16,6 @@
 // License along with this library; if not, write to the Free Software
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 
-use std::convert::TryInto;
 use std::marker::PhantomData;
 
 macro_rules! mpv_cstr_to_str {
@@ -27,10 +26,24 @@ macro_rules! mpv_cstr_to_str {
     };
 }
 
+mod chapters;
 mod errors;
+mod input;
+mod messages;
+mod node;
+#[cfg(feature = "serde")]
+mod node_serde;
+mod osd;
+mod overlay;
+mod playlist;
+mod reply;
+mod tracks;
+mod watch;
 
 /// Event handling
 pub mod events;
+/// Typed mpv properties
+pub mod props;
 /// Custom protocols (`protocol://$url`) for playback
 #[cfg(feature = "protocols")]
 pub mod protocol;
@@ -38,16 +51,37 @@ pub mod protocol;
 #[cfg(feature = "render")]
 pub mod render;
 
+pub use self::chapters::{Chapter, Edition};
 pub use self::errors::*;
+use self::input::KeyBindings;
+pub use self::input::{InputSection, KeyEvent, KeyState, SectionMode};
+use self::messages::MessageBus;
+pub use self::messages::{MessageHandler, ScriptReply};
+pub use self::node::Node;
+use self::node::NodeArena;
+pub use self::osd::OsdOverlay;
+pub use self::overlay::Overlay;
+use self::overlay::OverlayIds;
+pub use self::playlist::{Playlist, PlaylistChange, PlaylistEntry, PlaylistWatcher};
+use self::reply::PendingReplies;
+pub use self::reply::{AsyncCommand, AsyncReply};
+pub use self::tracks::{Track, TrackChange, TrackType, Tracks, TracksWatcher};
+use self::watch::Watchers;
+pub use self::watch::{Diff, DiffWatcher, PropertyWatcher};
 use super::*;
 
+use self::events::{HookHandle, OwnedPropertyData, PropertyData};
 use std::{
+    convert::{TryFrom, TryInto},
     ffi::CString,
     mem::MaybeUninit,
     ops::Deref,
     os::raw as ctype,
     ptr::{self, NonNull},
-    sync::atomic::AtomicBool,
+    sync::{
+        atomic::{AtomicBool, AtomicU64},
+        Arc,
+    },
 };
 
 fn mpv_err<T>(ret: T, err: ctype::c_int) -> Result<T> {
@@ -105,59 +139,50 @@ pub enum MpvNodeValue<'a> {
     Flag(bool),
     Int64(i64),
     Double(f64),
+    ByteArray(&'a [u8]),
     Array(MpvNodeArrayIter<'a>),
     Map(MpvNodeMapIter<'a>),
     None,
 }
 
 #[derive(Debug)]
-pub struct MpvNodeArrayIter<'parent> {
-    curr: i32,
-    list: libmpv_sys::mpv_node_list,
-    _does_not_outlive: PhantomData<&'parent MpvNode>,
+pub struct MpvNodeArrayIter<'a> {
+    curr: usize,
+    values: &'a [libmpv_sys::mpv_node],
 }
 
-impl Iterator for MpvNodeArrayIter<'_> {
-    type Item = MpvNode;
+impl<'a> Iterator for MpvNodeArrayIter<'a> {
+    type Item = MpvNodeRef<'a>;
 
-    fn next(&mut self) -> Option<MpvNode> {
-        if self.curr >= self.list.num {
-            None
-        } else {
-            let offset = self.curr.try_into().ok()?;
-            self.curr += 1;
-            Some(MpvNode(unsafe { *self.list.values.offset(offset) }))
-        }
+    fn next(&mut self) -> Option<MpvNodeRef<'a>> {
+        let value = self.values.get(self.curr)?;
+        self.curr += 1;
+        Some(MpvNodeRef(value))
     }
 }
 
 #[derive(Debug)]
-pub struct MpvNodeMapIter<'parent> {
-    curr: i32,
-    list: libmpv_sys::mpv_node_list,
-    _does_not_outlive: PhantomData<&'parent MpvNode>,
+pub struct MpvNodeMapIter<'a> {
+    curr: usize,
+    values: &'a [libmpv_sys::mpv_node],
+    keys: *const *mut ctype::c_char,
 }
 
-impl<'parent> Iterator for MpvNodeMapIter<'parent> {
-    type Item = (&'parent str, MpvNode);
+impl<'a> Iterator for MpvNodeMapIter<'a> {
+    type Item = (&'a str, MpvNodeRef<'a>);
 
-    fn next(&mut self) -> Option<(&'parent str, MpvNode)> {
-        if self.curr >= self.list.num {
-            None
-        } else {
-            let offset = self.curr.try_into().ok()?;
-            let (key, value) = unsafe {
-                (
-                    mpv_cstr_to_str!(*self.list.keys.offset(offset)),
-                    *self.list.values.offset(offset),
-                )
-            };
-            self.curr += 1;
-            Some((key.ok()?, MpvNode(value)))
-        }
+    fn next(&mut self) -> Option<(&'a str, MpvNodeRef<'a>)> {
+        let value = self.values.get(self.curr)?;
+        let key = unsafe { mpv_cstr_to_str!(*self.keys.add(self.curr)) };
+        self.curr += 1;
+        Some((key.ok()?, MpvNodeRef(value)))
     }
 }
 
+/// A node tree that was allocated by mpv, and is freed when dropped.
+///
+/// Its contents, and those of its children, are accessed through `MpvNodeRef`s that borrow from
+/// the tree.
 #[derive(Debug)]
 pub struct MpvNode(libmpv_sys::mpv_node);
 
@@ -168,7 +193,63 @@ impl Drop for MpvNode {
 }
 
 impl MpvNode {
+    /// Borrow the root of the tree.
+    pub fn as_node_ref(&self) -> MpvNodeRef<'_> {
+        MpvNodeRef(&self.0)
+    }
+
     pub fn value(&self) -> Result<MpvNodeValue<'_>> {
+        self.as_node_ref().value()
+    }
+
+    pub fn to_bool(&self) -> Option<bool> {
+        self.as_node_ref().to_bool()
+    }
+
+    pub fn to_i64(&self) -> Option<i64> {
+        self.as_node_ref().to_i64()
+    }
+
+    pub fn to_f64(&self) -> Option<f64> {
+        self.as_node_ref().to_f64()
+    }
+
+    pub fn to_str(&self) -> Option<&str> {
+        self.as_node_ref().to_str()
+    }
+
+    pub fn to_bytes(&self) -> Option<&[u8]> {
+        self.as_node_ref().to_bytes()
+    }
+
+    pub fn to_array(&self) -> Option<MpvNodeArrayIter<'_>> {
+        self.as_node_ref().to_array()
+    }
+
+    pub fn to_map(&self) -> Option<MpvNodeMapIter<'_>> {
+        self.as_node_ref().to_map()
+    }
+
+    /// Look up the value of `key`, if this is a map.
+    pub fn get(&self, key: &str) -> Option<MpvNodeRef<'_>> {
+        self.as_node_ref().get(key)
+    }
+}
+
+/// A node borrowed from a tree that is owned elsewhere, e.g. by an `MpvNode` or by an event.
+///
+/// Use `to_node` to deep-copy it into an owned `Node`.
+#[derive(Clone, Copy, Debug)]
+pub struct MpvNodeRef<'a>(&'a libmpv_sys::mpv_node);
+
+impl<'a> MpvNodeRef<'a> {
+    /// # Safety
+    /// `node` has to be a valid node, that outlives `'a`.
+    pub(crate) unsafe fn from_raw(node: *const libmpv_sys::mpv_node) -> MpvNodeRef<'a> {
+        MpvNodeRef(&*node)
+    }
+
+    pub fn value(self) -> Result<MpvNodeValue<'a>> {
         let node = self.0;
         Ok(match node.format {
             mpv_format::Flag => MpvNodeValue::Flag(unsafe { node.u.flag } == 1),
@@ -178,38 +259,49 @@ impl MpvNode {
                 let text = unsafe { mpv_cstr_to_str!(node.u.string) }?;
                 MpvNodeValue::String(text)
             }
+            mpv_format::ByteArray => {
+                let ba = unsafe { &*node.u.ba };
+                if ba.size == 0 {
+                    MpvNodeValue::ByteArray(&[])
+                } else {
+                    MpvNodeValue::ByteArray(unsafe {
+                        std::slice::from_raw_parts(ba.data as *const u8, ba.size as _)
+                    })
+                }
+            }
 
             mpv_format::Array => MpvNodeValue::Array(MpvNodeArrayIter {
-                list: unsafe { *node.u.list },
                 curr: 0,
-                _does_not_outlive: PhantomData,
+                values: unsafe { node_list_values(node.u.list) },
             }),
 
             mpv_format::Map => MpvNodeValue::Map(MpvNodeMapIter {
-                list: unsafe { *node.u.list },
                 curr: 0,
-                _does_not_outlive: PhantomData,
+                values: unsafe { node_list_values(node.u.list) },
+                keys: unsafe { (*node.u.list).keys },
             }),
             mpv_format::None => MpvNodeValue::None,
             _ => return Err(Error::Raw(mpv_error::PropertyError)),
         })
     }
 
-    pub fn to_bool(&self) -> Option<bool> {
+    pub fn to_bool(self) -> Option<bool> {
         if let MpvNodeValue::Flag(value) = self.value().ok()? {
             Some(value)
         } else {
             None
         }
     }
-    pub fn to_i64(&self) -> Option<i64> {
+
+    pub fn to_i64(self) -> Option<i64> {
         if let MpvNodeValue::Int64(value) = self.value().ok()? {
             Some(value)
         } else {
             None
         }
     }
-    pub fn to_f64(&self) -> Option<f64> {
+
+    pub fn to_f64(self) -> Option<f64> {
         if let MpvNodeValue::Double(value) = self.value().ok()? {
             Some(value)
         } else {
@@ -217,7 +309,7 @@ impl MpvNode {
         }
     }
 
-    pub fn to_str(&self) -> Option<&str> {
+    pub fn to_str(self) -> Option<&'a str> {
         if let MpvNodeValue::String(value) = self.value().ok()? {
             Some(value)
         } else {
@@ -225,7 +317,15 @@ impl MpvNode {
         }
     }
 
-    pub fn to_array(&self) -> Option<MpvNodeArrayIter<'_>> {
+    pub fn to_bytes(self) -> Option<&'a [u8]> {
+        if let MpvNodeValue::ByteArray(value) = self.value().ok()? {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    pub fn to_array(self) -> Option<MpvNodeArrayIter<'a>> {
         if let MpvNodeValue::Array(value) = self.value().ok()? {
             Some(value)
         } else {
@@ -233,13 +333,34 @@ impl MpvNode {
         }
     }
 
-    pub fn to_map(&self) -> Option<MpvNodeMapIter<'_>> {
+    pub fn to_map(self) -> Option<MpvNodeMapIter<'a>> {
         if let MpvNodeValue::Map(value) = self.value().ok()? {
             Some(value)
         } else {
             None
         }
     }
+
+    /// Look up the value of `key`, if this is a map.
+    pub fn get(self, key: &str) -> Option<MpvNodeRef<'a>> {
+        self.to_map()?
+            .find_map(|(k, value)| if k == key { Some(value) } else { None })
+    }
+}
+
+/// The values of an array or map node.
+///
+/// # Safety
+/// `list` has to be a valid list, that outlives `'a`.
+unsafe fn node_list_values<'a>(
+    list: *const libmpv_sys::mpv_node_list,
+) -> &'a [libmpv_sys::mpv_node] {
+    let list = &*list;
+    if list.num <= 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(list.values, list.num as _)
+    }
 }
 
 unsafe impl GetData for MpvNode {
@@ -390,6 +511,105 @@ impl FileState {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+/// What a screenshot contains.
+pub enum ScreenshotMode {
+    /// The video in its original resolution, with subtitles.
+    Subtitles,
+    /// The video in its original resolution, typically without OSD or subtitles.
+    Video,
+    /// The contents of the mpv window, typically scaled, with OSD and subtitles.
+    Window,
+}
+
+impl ScreenshotMode {
+    fn val(&self) -> &str {
+        match *self {
+            ScreenshotMode::Subtitles => "subtitles",
+            ScreenshotMode::Video => "video",
+            ScreenshotMode::Window => "window",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// The result of `Mpv::subprocess`.
+pub struct SubprocessResult {
+    /// The exit status of the process, negative if the process could not be started or was
+    /// killed.
+    pub status: i64,
+    /// Captured standard output.
+    pub stdout: Vec<u8>,
+    /// Captured standard error.
+    pub stderr: Vec<u8>,
+    /// `"success"`, `"killed"`, `"init"` (could not be started) or `"error"`.
+    pub error_string: String,
+    /// Whether the process was killed because of `AsyncCommand::abort`, or because playback
+    /// stopped.
+    pub killed_by_us: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// An image returned by `Mpv::screenshot_raw`.
+pub struct RawScreenshot {
+    pub width: i64,
+    pub height: i64,
+    /// Number of bytes per row of pixels, may include padding.
+    pub stride: i64,
+    /// The pixel format, currently always `"bgr0"`.
+    pub format: String,
+    pub data: Vec<u8>,
+}
+
+/// A `NULL` terminated argument vector, as taken by `mpv_command` and friends.
+struct Argv {
+    _args: Vec<CString>,
+    ptrs: Vec<*const ctype::c_char>,
+}
+
+impl Argv {
+    fn new(name: &str, args: &[&str]) -> Result<Argv> {
+        let mut owned = Vec::with_capacity(args.len() + 1);
+        owned.push(CString::new(name)?);
+        for arg in args {
+            owned.push(CString::new(*arg)?);
+        }
+
+        let mut ptrs: Vec<_> = owned.iter().map(|arg| arg.as_ptr()).collect();
+        ptrs.push(ptr::null());
+        Ok(Argv { _args: owned, ptrs })
+    }
+
+    fn as_mut_ptr(&mut self) -> *mut *const ctype::c_char {
+        self.ptrs.as_mut_ptr()
+    }
+}
+
+/// Render a command node as the words of the equivalent command line, for error messages.
+fn command_words(command: &Node) -> Vec<String> {
+    fn word(node: &Node) -> String {
+        match node {
+            Node::String(s) => s.clone(),
+            Node::Flag(b) => (if *b { "yes" } else { "no" }).to_owned(),
+            Node::Int64(i) => i.to_string(),
+            Node::Double(d) => d.to_string(),
+            other => format!("{:?}", other),
+        }
+    }
+
+    match command {
+        Node::Array(args) => args.iter().map(word).collect(),
+        Node::Map(args) => args
+            .iter()
+            .map(|(key, value)| match key.as_str() {
+                "name" => word(value),
+                _ => format!("{}={}", key, word(value)),
+            })
+            .collect(),
+        other => vec![word(other)],
+    }
+}
+
 /// Context passed to the `initializer` of `Mpv::with_initialzer`.
 pub struct MpvInitializer {
     ctx: *mut libmpv_sys::mpv_handle,
@@ -398,13 +618,14 @@ pub struct MpvInitializer {
 impl MpvInitializer {
     /// Set the value of a property.
     pub fn set_property<T: SetData>(&self, name: &str, data: T) -> Result<()> {
-        let name = CString::new(name)?;
+        let c_name = CString::new(name)?;
         let format = T::get_format().as_mpv_format() as _;
         data.call_as_c_void(|ptr| {
             mpv_err((), unsafe {
-                libmpv_sys::mpv_set_property(self.ctx, name.as_ptr(), format, ptr)
+                libmpv_sys::mpv_set_property(self.ctx, c_name.as_ptr(), format, ptr)
             })
         })
+        .map_err(|e| e.for_property(name))
     }
 }
 
@@ -415,6 +636,14 @@ pub struct Mpv {
     events_guard: AtomicBool,
     #[cfg(feature = "protocols")]
     protocols_guard: AtomicBool,
+    replies: PendingReplies,
+    watchers: Watchers,
+    messages: MessageBus,
+    key_bindings: KeyBindings,
+    hook_handle: Arc<HookHandle>,
+    osd_overlay_ids: AtomicU64,
+    overlay_ids: Arc<OverlayIds>,
+    is_client: bool,
 }
 
 unsafe impl Send for Mpv {}
@@ -422,12 +651,36 @@ unsafe impl Sync for Mpv {}
 
 impl Drop for Mpv {
     fn drop(&mut self) {
+        self.hook_handle.clear();
         unsafe {
-            libmpv_sys::mpv_terminate_destroy(self.ctx.as_ptr());
+            libmpv_sys::mpv_wait_async_requests(self.ctx.as_ptr());
+            if self.is_client {
+                libmpv_sys::mpv_destroy(self.ctx.as_ptr());
+            } else {
+                libmpv_sys::mpv_terminate_destroy(self.ctx.as_ptr());
+            }
         }
     }
 }
 
+/// An additional client handle to the core of an `Mpv`, created by `Mpv::create_client` or
+/// `Mpv::create_weak_client`.
+///
+/// Every client has its own event queue, observed properties and asynchronous requests, and can
+/// be used like the main `Mpv` it derefs to. Dropping a client only destroys its handle.
+pub struct MpvClient<'parent> {
+    mpv: Mpv,
+    _does_not_outlive: PhantomData<&'parent Mpv>,
+}
+
+impl<'parent> Deref for MpvClient<'parent> {
+    type Target = Mpv;
+
+    fn deref(&self) -> &Mpv {
+        &self.mpv
+    }
+}
+
 impl Mpv {
     /// Create a new `Mpv`.
     /// The default settings can be probed by running: `$ mpv --show-profile=libmpv`.
@@ -459,14 +712,80 @@ impl Mpv {
             err
         })?;
 
-        Ok(Mpv {
-            ctx: unsafe { NonNull::new_unchecked(ctx) },
+        Ok(Mpv::from_handle(
+            unsafe { NonNull::new_unchecked(ctx) },
+            Arc::new(OverlayIds::new()),
+            false,
+        ))
+    }
+
+    fn from_handle(
+        ctx: NonNull<libmpv_sys::mpv_handle>,
+        overlay_ids: Arc<OverlayIds>,
+        is_client: bool,
+    ) -> Mpv {
+        Mpv {
+            ctx,
             events_guard: AtomicBool::new(false),
             #[cfg(feature = "protocols")]
             protocols_guard: AtomicBool::new(false),
+            replies: PendingReplies::new(),
+            watchers: Watchers::new(),
+            messages: MessageBus::new(),
+            key_bindings: KeyBindings::new(),
+            hook_handle: HookHandle::new(ctx),
+            osd_overlay_ids: AtomicU64::new(1),
+            overlay_ids,
+            is_client,
+        }
+    }
+
+    /// Create an additional client handle to this core. If `name` is already in use, or contains
+    /// characters other than alphanumerics and `_`, mpv modifies it to fit.
+    ///
+    /// Note that dropping the main `Mpv` blocks until all its clients have been dropped.
+    pub fn create_client(&self, name: &str) -> Result<MpvClient<'_>> {
+        self.new_client(name, libmpv_sys::mpv_create_client)
+    }
+
+    /// Same as `create_client`, but the client is a weak reference, using
+    /// `mpv_create_weak_client`.
+    pub fn create_weak_client(&self, name: &str) -> Result<MpvClient<'_>> {
+        self.new_client(name, libmpv_sys::mpv_create_weak_client)
+    }
+
+    fn new_client(
+        &self,
+        name: &str,
+        create: unsafe extern "C" fn(
+            *mut libmpv_sys::mpv_handle,
+            *const ctype::c_char,
+        ) -> *mut libmpv_sys::mpv_handle,
+    ) -> Result<MpvClient<'_>> {
+        let name = CString::new(name)?;
+        let ctx = unsafe { create(self.ctx.as_ptr(), name.as_ptr()) };
+        Ok(MpvClient {
+            mpv: Mpv::from_handle(
+                NonNull::new(ctx).ok_or(Error::Null)?,
+                self.overlay_ids.clone(),
+                true,
+            ),
+            _does_not_outlive: PhantomData,
         })
     }
 
+    /// The name of this client handle, `"main"` for the handle created by `Mpv::new`.
+    /// Other clients can use it to address this handle, e.g. with `script-message-to`.
+    pub fn client_name(&self) -> Result<&str> {
+        unsafe { mpv_cstr_to_str!(libmpv_sys::mpv_client_name(self.ctx.as_ptr())) }
+    }
+
+    /// The unique, positive ID of this client handle. APIs that take a client name also accept
+    /// it in the form `@<id>`.
+    pub fn client_id(&self) -> i64 {
+        unsafe { libmpv_sys::mpv_client_id(self.ctx.as_ptr()) }
+    }
+
     /// Load a configuration file. The path has to be absolute, and a file.
     pub fn load_config(&self, path: &str) -> Result<()> {
         let file = CString::new(path)?.into_raw();
@@ -481,6 +800,7 @@ impl Mpv {
     /// so that the syntax is the same as described in the [manual for the input.conf](https://mpv.io/manual/master/#list-of-input-commands).
     ///
     /// Note that you may have to escape strings with `""` when they contain spaces.
+    /// Use `command_argv` to pass arguments, such as file names, verbatim instead.
     pub fn command(&self, name: &str, args: &[&str]) -> Result<()> {
         let mut cmd = name.to_owned();
 
@@ -493,29 +813,203 @@ impl Mpv {
         mpv_err((), unsafe {
             libmpv_sys::mpv_command_string(self.ctx.as_ptr(), raw.as_ptr())
         })
+        .map_err(|e| e.for_command(name, args))
+    }
+
+    /// Send a command to the `Mpv` instance. This uses `mpv_command` internally, so every
+    /// argument is passed to mpv as a separate string, and is never parsed or unquoted.
+    /// Arguments may thus contain spaces, quotes, backslashes or newlines.
+    pub fn command_argv(&self, name: &str, args: &[&str]) -> Result<()> {
+        let mut argv = Argv::new(name, args)?;
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_command(self.ctx.as_ptr(), argv.as_mut_ptr())
+        })
+        .map_err(|e| e.for_command(name, args))
+    }
+
+    /// Send a command to the `Mpv` instance, like `command_argv`, and return its result, using
+    /// `mpv_command_ret`. Most commands return `MpvNodeValue::None`.
+    pub fn command_ret(&self, name: &str, args: &[&str]) -> Result<MpvNode> {
+        let mut argv = Argv::new(name, args)?;
+        let mut result = MaybeUninit::uninit();
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_command_ret(self.ctx.as_ptr(), argv.as_mut_ptr(), result.as_mut_ptr())
+        })
+        .map_err(|e| e.for_command(name, args))?;
+        Ok(MpvNode(unsafe { result.assume_init() }))
+    }
+
+    /// Send a command given as node to the `Mpv` instance, and return its result, using
+    /// `mpv_command_node`. The command is either a `Node::Array` of the command name followed by
+    /// its arguments, or a `Node::Map` of named arguments, whose `name` entry is the command name.
+    pub fn command_node(&self, command: Node) -> Result<MpvNode> {
+        let mut arena = NodeArena::default();
+        let mut raw = arena.node(&command)?;
+        let mut result = MaybeUninit::uninit();
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_command_node(self.ctx.as_ptr(), &mut raw, result.as_mut_ptr())
+        })
+        .map_err(|e| Error::Command {
+            args: command_words(&command),
+            error: Box::new(e),
+        })?;
+        Ok(MpvNode(unsafe { result.assume_init() }))
+    }
+
+    /// Send a command to the `Mpv` instance without waiting for it to finish, using
+    /// `mpv_command_async`. Unlike `command`, every argument is passed to mpv as is, so
+    /// no quoting is necessary.
+    ///
+    /// The returned handle completes once the matching `Event::CommandReply` is received by
+    /// `EventContext::wait_event`, and can be used to abort the command.
+    pub fn command_async(&self, name: &str, args: &[&str]) -> Result<AsyncCommand<'_>> {
+        let mut argv = Argv::new(name, args)?;
+        let args: Vec<String> = args.iter().map(|&arg| arg.to_owned()).collect();
+        let context = name.to_owned();
+        AsyncCommand::start(
+            self,
+            |_| Ok(()),
+            move |e| e.for_command(&context, &args),
+            |reply_userdata| {
+                mpv_err((), unsafe {
+                    libmpv_sys::mpv_command_async(
+                        self.ctx.as_ptr(),
+                        reply_userdata,
+                        argv.as_mut_ptr(),
+                    )
+                })
+            },
+        )
+    }
+
+    /// Send a command given as node to the `Mpv` instance without waiting for it to finish, using
+    /// `mpv_command_node_async`. The command has the same form as for `command_node`.
+    ///
+    /// The returned handle completes with the result of the command once the matching
+    /// `Event::CommandReply` is received by `EventContext::wait_event`, and can be used to abort
+    /// the command.
+    pub fn command_node_async(&self, command: Node) -> Result<AsyncReply<'_, Node>> {
+        let mut arena = NodeArena::default();
+        let mut raw = arena.node(&command)?;
+        let args = command_words(&command);
+        AsyncReply::start(
+            self,
+            |event| {
+                let command = unsafe { &*(event.data as *const libmpv_sys::mpv_event_command) };
+                unsafe { Node::from_raw(&command.result) }
+            },
+            move |e| Error::Command {
+                args: args.clone(),
+                error: Box::new(e),
+            },
+            |reply_userdata| {
+                mpv_err((), unsafe {
+                    libmpv_sys::mpv_command_node_async(self.ctx.as_ptr(), reply_userdata, &mut raw)
+                })
+            },
+        )
     }
 
     /// Set the value of a property.
     pub fn set_property<T: SetData>(&self, name: &str, data: T) -> Result<()> {
-        let name = CString::new(name)?;
+        let c_name = CString::new(name)?;
         let format = T::get_format().as_mpv_format() as _;
         data.call_as_c_void(|ptr| {
             mpv_err((), unsafe {
-                libmpv_sys::mpv_set_property(self.ctx.as_ptr(), name.as_ptr(), format, ptr)
+                libmpv_sys::mpv_set_property(self.ctx.as_ptr(), c_name.as_ptr(), format, ptr)
             })
         })
+        .map_err(|e| e.for_property(name))
     }
 
     /// Get the value of a property.
     pub fn get_property<T: GetData>(&self, name: &str) -> Result<T> {
-        let name = CString::new(name)?;
+        let c_name = CString::new(name)?;
 
         let format = T::get_format().as_mpv_format() as _;
         T::get_from_c_void(|ptr| {
             mpv_err((), unsafe {
-                libmpv_sys::mpv_get_property(self.ctx.as_ptr(), name.as_ptr(), format, ptr)
+                libmpv_sys::mpv_get_property(self.ctx.as_ptr(), c_name.as_ptr(), format, ptr)
             })
         })
+        .map_err(|e| e.for_property(name))
+    }
+
+    /// Get the value of a property as `Node`, and convert it to `T`.
+    pub(crate) fn get_node_property<T: TryFrom<Node, Error = Error>>(
+        &self,
+        name: &str,
+    ) -> Result<T> {
+        let node: Node = self.get_property(name)?;
+        node.try_into().map_err(|e: Error| e.for_property(name))
+    }
+
+    /// Get the value of a property without blocking, using `mpv_get_property_async`.
+    ///
+    /// The returned handle completes once the matching `Event::GetPropertyReply` is received by
+    /// `EventContext::wait_event`.
+    pub fn get_property_async<T>(&self, name: &str) -> Result<AsyncReply<'_, T>>
+    where
+        T: GetData + TryFrom<OwnedPropertyData, Error = Error> + Send + 'static,
+    {
+        let c_name = CString::new(name)?;
+        let format = T::get_format().as_mpv_format() as _;
+        let context = name.to_owned();
+        AsyncReply::start(
+            self,
+            |event| {
+                let property = unsafe { *(event.data as *mut libmpv_sys::mpv_event_property) };
+                if property.format == mpv_format::None {
+                    return Err(Error::Raw(mpv_error::PropertyUnavailable));
+                }
+                // SAFETY: safe because we are passing format + data from an mpv_event_property
+                let data = unsafe { PropertyData::from_raw(property.format, property.data) }?;
+                data.to_owned_data()?.try_into()
+            },
+            move |e| e.for_property(&context),
+            |reply_userdata| {
+                mpv_err((), unsafe {
+                    libmpv_sys::mpv_get_property_async(
+                        self.ctx.as_ptr(),
+                        reply_userdata,
+                        c_name.as_ptr(),
+                        format,
+                    )
+                })
+            },
+        )
+    }
+
+    /// Set the value of a property without blocking, using `mpv_set_property_async`.
+    ///
+    /// The returned handle completes once the matching `Event::SetPropertyReply` is received by
+    /// `EventContext::wait_event`.
+    pub fn set_property_async<T: SetData>(
+        &self,
+        name: &str,
+        data: T,
+    ) -> Result<AsyncReply<'_, ()>> {
+        let c_name = CString::new(name)?;
+        let format = T::get_format().as_mpv_format() as _;
+        let context = name.to_owned();
+        AsyncReply::start(
+            self,
+            |_| Ok(()),
+            move |e| e.for_property(&context),
+            |reply_userdata| {
+                data.call_as_c_void(|ptr| {
+                    mpv_err((), unsafe {
+                        libmpv_sys::mpv_set_property_async(
+                            self.ctx.as_ptr(),
+                            reply_userdata,
+                            c_name.as_ptr(),
+                            format,
+                            ptr,
+                        )
+                    })
+                })
+            },
+        )
     }
 
     /// Internal time in microseconds, this has an arbitrary offset, and will never go backwards.
@@ -530,18 +1024,18 @@ impl Mpv {
 
     /// Add -or subtract- any value from a property. Over/underflow clamps to max/min.
     pub fn add_property(&self, property: &str, value: isize) -> Result<()> {
-        self.command("add", &[property, &format!("{}", value)])
+        self.command_argv("add", &[property, &format!("{}", value)])
     }
 
     /// Cycle through a given property. `up` specifies direction. On
     /// overflow, set the property back to the minimum, on underflow set it to the maximum.
     pub fn cycle_property(&self, property: &str, up: bool) -> Result<()> {
-        self.command("cycle", &[property, if up { "up" } else { "down" }])
+        self.command_argv("cycle", &[property, if up { "up" } else { "down" }])
     }
 
     /// Multiply any property with any positive factor.
     pub fn multiply_property(&self, property: &str, factor: usize) -> Result<()> {
-        self.command("multiply", &[property, &format!("{}", factor)])
+        self.command_argv("multiply", &[property, &format!("{}", factor)])
     }
 
     /// Pause playback at runtime.
@@ -554,6 +1048,67 @@ impl Mpv {
         self.set_property("pause", false)
     }
 
+    /// Expand properties in `text`, as described in
+    /// [Property Expansion](https://mpv.io/manual/master/#property-expansion).
+    pub fn expand_text(&self, text: &str) -> Result<String> {
+        let ret = self.command_ret("expand-text", &[text])?;
+        ret.to_str()
+            .map(str::to_owned)
+            .ok_or(Error::Raw(mpv_error::PropertyFormat))
+    }
+
+    /// Expand a path's double-tilde placeholders, e.g. `~~home/` or `~~desktop/`.
+    pub fn expand_path(&self, path: &str) -> Result<String> {
+        let ret = self.command_ret("expand-path", &[path])?;
+        ret.to_str()
+            .map(str::to_owned)
+            .ok_or(Error::Raw(mpv_error::PropertyFormat))
+    }
+
+    /// Run an external process, and wait for it to terminate, capturing its standard output and
+    /// standard error. If `playback_only` is set, the process is killed when playback stops.
+    ///
+    /// The first element of `args` is the program to run, it is looked up in `PATH`. No shell is
+    /// involved. Note that this blocks until the process terminates; use `command_async` with
+    /// the `subprocess` command if that is not desired.
+    ///
+    /// Returns `Error::Raw(mpv_error::PropertyFormat)` if mpv did not report an exit status.
+    pub fn subprocess(&self, args: &[&str], playback_only: bool) -> Result<SubprocessResult> {
+        let cmd = Node::Map(vec![
+            ("name".to_owned(), "subprocess".into()),
+            ("args".to_owned(), args.to_vec().into()),
+            ("playback_only".to_owned(), playback_only.into()),
+            ("capture_stdout".to_owned(), true.into()),
+            ("capture_stderr".to_owned(), true.into()),
+        ]);
+
+        let ret = self.command_node(cmd)?;
+        let bytes = |key| {
+            ret.get(key)
+                .and_then(MpvNodeRef::to_bytes)
+                .unwrap_or_default()
+                .to_vec()
+        };
+
+        Ok(SubprocessResult {
+            status: ret
+                .get("status")
+                .and_then(MpvNodeRef::to_i64)
+                .ok_or(Error::Raw(mpv_error::PropertyFormat))?,
+            stdout: bytes("stdout"),
+            stderr: bytes("stderr"),
+            error_string: ret
+                .get("error_string")
+                .and_then(MpvNodeRef::to_str)
+                .unwrap_or_default()
+                .to_owned(),
+            killed_by_us: ret
+                .get("killed_by_us")
+                .and_then(MpvNodeRef::to_bool)
+                .unwrap_or(false),
+        })
+    }
+
     // --- Seek functions ---
     //
 
@@ -562,51 +1117,51 @@ impl Mpv {
     /// (https://mpv.io/manual/master/#command-interface-
     /// [relative|absolute|absolute-percent|relative-percent|exact|keyframes]).
     pub fn seek_forward(&self, secs: ctype::c_double) -> Result<()> {
-        self.command("seek", &[&format!("{}", secs), "relative"])
+        self.command_argv("seek", &[&format!("{}", secs), "relative"])
     }
 
     /// See `seek_forward`.
     pub fn seek_backward(&self, secs: ctype::c_double) -> Result<()> {
-        self.command("seek", &[&format!("-{}", secs), "relative"])
+        self.command_argv("seek", &[&format!("-{}", secs), "relative"])
     }
 
     /// Seek to a given absolute secs.
     pub fn seek_absolute(&self, secs: ctype::c_double) -> Result<()> {
-        self.command("seek", &[&format!("{}", secs), "absolute"])
+        self.command_argv("seek", &[&format!("{}", secs), "absolute"])
     }
 
     /// Seek to a given relative percent position (may be negative).
     /// If `percent` of the playtime is bigger than the remaining playtime, the next file is played.
     /// out of bounds values are clamped to either 0 or 100.
     pub fn seek_percent(&self, percent: isize) -> Result<()> {
-        self.command("seek", &[&format!("{}", percent), "relative-percent"])
+        self.command_argv("seek", &[&format!("{}", percent), "relative-percent"])
     }
 
     /// Seek to the given percentage of the playtime.
     pub fn seek_percent_absolute(&self, percent: usize) -> Result<()> {
-        self.command("seek", &[&format!("{}", percent), "relative-percent"])
+        self.command_argv("seek", &[&format!("{}", percent), "relative-percent"])
     }
 
     /// Revert the previous `seek_` call, can also revert itself.
     pub fn seek_revert(&self) -> Result<()> {
-        self.command("revert-seek", &[])
+        self.command_argv("revert-seek", &[])
     }
 
     /// Mark the current position as the position that will be seeked to by `seek_revert`.
     pub fn seek_revert_mark(&self) -> Result<()> {
-        self.command("revert-seek", &["mark"])
+        self.command_argv("revert-seek", &["mark"])
     }
 
     /// Seek exactly one frame, and pause.
     /// Noop on audio only streams.
     pub fn seek_frame(&self) -> Result<()> {
-        self.command("frame-step", &[])
+        self.command_argv("frame-step", &[])
     }
 
     /// See `seek_frame`.
     /// [Note performance considerations.](https://mpv.io/manual/master/#command-interface-frame-back-step)
     pub fn seek_frame_backward(&self) -> Result<()> {
-        self.command("frame-back-step", &[])
+        self.command_argv("frame-back-step", &[])
     }
 
     // --- Screenshot functions ---
@@ -622,9 +1177,9 @@ impl Mpv {
     /// described in [Property Expansion](https://mpv.io/manual/master/#property-expansion)."
     pub fn screenshot_subtitles(&self, path: Option<&str>) -> Result<()> {
         if let Some(path) = path {
-            self.command("screenshot", &[&format!("\"{}\"", path), "subtitles"])
+            self.command_argv("screenshot", &[path, "subtitles"])
         } else {
-            self.command("screenshot", &["subtitles"])
+            self.command_argv("screenshot", &["subtitles"])
         }
     }
 
@@ -632,9 +1187,9 @@ impl Mpv {
     /// depends on the selected video output."
     pub fn screenshot_video(&self, path: Option<&str>) -> Result<()> {
         if let Some(path) = path {
-            self.command("screenshot", &[&format!("\"{}\"", path), "video"])
+            self.command_argv("screenshot", &[path, "video"])
         } else {
-            self.command("screenshot", &["video"])
+            self.command_argv("screenshot", &["video"])
         }
     }
 
@@ -643,35 +1198,60 @@ impl Mpv {
     /// this will act like video.".
     pub fn screenshot_window(&self, path: Option<&str>) -> Result<()> {
         if let Some(path) = path {
-            self.command("screenshot", &[&format!("\"{}\"", path), "window"])
+            self.command_argv("screenshot", &[path, "window"])
         } else {
-            self.command("screenshot", &["window"])
+            self.command_argv("screenshot", &["window"])
         }
     }
 
+    /// Take a screenshot, and return the image data instead of saving it to a file.
+    pub fn screenshot_raw(&self, mode: ScreenshotMode) -> Result<RawScreenshot> {
+        let ret = self.command_ret("screenshot-raw", &[mode.val()])?;
+        let field = |key| ret.get(key).ok_or(Error::Raw(mpv_error::PropertyFormat));
+        let int = |key| {
+            field(key)?
+                .to_i64()
+                .ok_or(Error::Raw(mpv_error::PropertyFormat))
+        };
+
+        Ok(RawScreenshot {
+            width: int("w")?,
+            height: int("h")?,
+            stride: int("stride")?,
+            format: field("format")?
+                .to_str()
+                .ok_or(Error::Raw(mpv_error::PropertyFormat))?
+                .to_owned(),
+            data: field("data")?
+                .to_bytes()
+                .ok_or(Error::Raw(mpv_error::PropertyFormat))?
+                .to_vec(),
+        })
+    }
+
     // --- Playlist functions ---
     //
 
     /// Play the next item of the current playlist.
     /// Does nothing if the current item is the last item.
     pub fn playlist_next_weak(&self) -> Result<()> {
-        self.command("playlist-next", &["weak"])
+        self.command_argv("playlist-next", &["weak"])
     }
 
     /// Play the next item of the current playlist.
     /// Terminates playback if the current item is the last item.
     pub fn playlist_next_force(&self) -> Result<()> {
-        self.command("playlist-next", &["force"])
+        self.command_argv("playlist-next", &["force"])
     }
 
     /// See `playlist_next_weak`.
     pub fn playlist_previous_weak(&self) -> Result<()> {
-        self.command("playlist-prev", &["weak"])
+        self.command_argv("playlist-prev", &["weak"])
     }
 
     /// See `playlist_next_force`.
     pub fn playlist_previous_force(&self) -> Result<()> {
-        self.command("playlist-prev", &["force"])
+        self.command_argv("playlist-prev", &["force"])
     }
 
     /// The given files are loaded sequentially, returning the index of the current file
@@ -688,55 +1268,85 @@ impl Mpv {
     /// [specifics](https://github.com/mpv-player/mpv/issues/4089).
     pub fn playlist_load_files(&self, files: &[(&str, FileState, Option<&str>)]) -> Result<()> {
         for (i, elem) in files.iter().enumerate() {
-            let args = elem.2.unwrap_or("");
-
-            let ret = self.command(
-                "loadfile",
-                &[&format!("\"{}\"", elem.0), elem.1.val(), args],
-            );
+            let ret = if let Some(options) = elem.2 {
+                self.command_argv("loadfile", &[elem.0, elem.1.val(), options])
+            } else {
+                self.command_argv("loadfile", &[elem.0, elem.1.val()])
+            };
 
             if let Err(err) = ret {
                 return Err(Error::Loadfiles {
                     index: i,
-                    error: ::std::rc::Rc::new(err),
+                    error: Box::new(err),
                 });
             }
         }
         Ok(())
     }
 
+    /// Load a single file, like `playlist_load_files`, and return the playlist entry ID
+    /// the new entry was assigned.
+    pub fn playlist_load_file(
+        &self,
+        path: &str,
+        state: FileState,
+        options: Option<&str>,
+    ) -> Result<i64> {
+        let ret = if let Some(options) = options {
+            self.command_ret("loadfile", &[path, state.val(), options])?
+        } else {
+            self.command_ret("loadfile", &[path, state.val()])?
+        };
+        ret.get("playlist_entry_id")
+            .and_then(MpvNodeRef::to_i64)
+            .ok_or(Error::Raw(mpv_error::PropertyFormat))
+    }
+
     /// Load the given playlist file, that either replaces the current playlist, or appends to it.
     pub fn playlist_load_list(&self, path: &str, replace: bool) -> Result<()> {
         if replace {
-            self.command("loadlist", &[&format!("\"{}\"", path), "replace"])
+            self.command_argv("loadlist", &[path, "replace"])
         } else {
-            self.command("loadlist", &[&format!("\"{}\"", path), "append"])
+            self.command_argv("loadlist", &[path, "append"])
         }
     }
 
     /// Remove every, except the current, item from the playlist.
     pub fn playlist_clear(&self) -> Result<()> {
-        self.command("playlist-clear", &[])
+        self.command_argv("playlist-clear", &[])
     }
 
     /// Remove the currently selected item from the playlist.
     pub fn playlist_remove_current(&self) -> Result<()> {
-        self.command("playlist-remove", &["current"])
+        self.command_argv("playlist-remove", &["current"])
     }
 
     /// Remove item at `position` from the playlist.
     pub fn playlist_remove_index(&self, position: usize) -> Result<()> {
-        self.command("playlist-remove", &[&format!("{}", position)])
+        self.command_argv("playlist-remove", &[&format!("{}", position)])
     }
 
     /// Move item `old` to the position of item `new`.
     pub fn playlist_move(&self, old: usize, new: usize) -> Result<()> {
-        self.command("playlist-move", &[&format!("{}", new), &format!("{}", old)])
+        self.command_argv("playlist-move", &[&format!("{}", new), &format!("{}", old)])
     }
 
     /// Shuffle the playlist.
     pub fn playlist_shuffle(&self) -> Result<()> {
-        self.command("playlist-shuffle", &[])
+        self.command_argv("playlist-shuffle", &[])
+    }
+
+    /// Undo a previous `playlist_shuffle`.
+    pub fn playlist_unshuffle(&self) -> Result<()> {
+        self.command_argv("playlist-unshuffle", &[])
+    }
+
+    /// Start playback of the item at `position`, or stop playback if it is `None`.
+    pub fn playlist_play_index(&self, position: Option<usize>) -> Result<()> {
+        match position {
+            Some(position) => self.command_argv("playlist-play-index", &[&position.to_string()]),
+            None => self.command_argv("playlist-play-index", &["none"]),
+        }
     }
 
     // --- Subtitle functions ---
@@ -754,12 +1364,10 @@ impl Mpv {
         lang: Option<&str>,
     ) -> Result<()> {
         match (title, lang) {
-            (None, None) => self.command("sub-add", &[&format!("\"{}\"", path), "select"]),
-            (Some(t), None) => self.command("sub-add", &[&format!("\"{}\"", path), "select", t]),
+            (None, None) => self.command_argv("sub-add", &[path, "select"]),
+            (Some(t), None) => self.command_argv("sub-add", &[path, "select", t]),
             (None, Some(_)) => panic!("Given subtitle language, but missing title"),
-            (Some(t), Some(l)) => {
-                self.command("sub-add", &[&format!("\"{}\"", path), "select", t, l])
-            }
+            (Some(t), Some(l)) => self.command_argv("sub-add", &[path, "select", t, l]),
         }
     }
 
@@ -777,11 +1385,9 @@ impl Mpv {
         lang: Option<&str>,
     ) -> Result<()> {
         match (title, lang) {
-            (None, None) => self.command("sub-add", &[&format!("\"{}\"", path), "auto"]),
-            (Some(t), None) => self.command("sub-add", &[&format!("\"{}\"", path), "auto", t]),
-            (Some(t), Some(l)) => {
-                self.command("sub-add", &[&format!("\"{}\"", path), "auto", t, l])
-            }
+            (None, None) => self.command_argv("sub-add", &[path, "auto"]),
+            (Some(t), None) => self.command_argv("sub-add", &[path, "auto", t]),
+            (Some(t), Some(l)) => self.command_argv("sub-add", &[path, "auto", t, l]),
             (None, Some(_)) => panic!("Given subtitle language, but missing title"),
         }
     }
@@ -791,16 +1397,16 @@ impl Mpv {
     /// (In this case, title/language are ignored, and if the [sub] was changed since it was loaded,
     /// these changes won't be reflected.)".
     pub fn subtitle_add_cached(&self, path: &str) -> Result<()> {
-        self.command("sub-add", &[&format!("\"{}\"", path), "cached"])
+        self.command_argv("sub-add", &[path, "cached"])
     }
 
     /// "Remove the given subtitle track. If the id argument is missing, remove the current
     /// track. (Works on external subtitle files only.)"
     pub fn subtitle_remove(&self, index: Option<usize>) -> Result<()> {
         if let Some(idx) = index {
-            self.command("sub-remove", &[&format!("{}", idx)])
+            self.command_argv("sub-remove", &[&format!("{}", idx)])
         } else {
-            self.command("sub-remove", &[])
+            self.command_argv("sub-remove", &[])
         }
     }
 
@@ -808,16 +1414,16 @@ impl Mpv {
     /// track. (Works on external subtitle files only.)"
     pub fn subtitle_reload(&self, index: Option<usize>) -> Result<()> {
         if let Some(idx) = index {
-            self.command("sub-reload", &[&format!("{}", idx)])
+            self.command_argv("sub-reload", &[&format!("{}", idx)])
         } else {
-            self.command("sub-reload", &[])
+            self.command_argv("sub-reload", &[])
         }
     }
 
     /// "Change subtitle timing such, that the subtitle event after the next `isize` subtitle
     /// events is displayed. `isize` can be negative to step backwards."
     pub fn subtitle_step(&self, skip: isize) -> Result<()> {
-        self.command("sub-step", &[&format!("{}", skip)])
+        self.command_argv("sub-step", &[&format!("{}", skip)])
     }
 
     /// "Seek to the next subtitle. This is similar to sub-step, except that it seeks video and
@@ -825,11 +1431,11 @@ impl Mpv {
     /// For embedded subtitles (like with matroska), this works only with subtitle events that
     /// have already been displayed, or are within a short prefetch range."
     pub fn subtitle_seek_forward(&self) -> Result<()> {
-        self.command("sub-seek", &["1"])
+        self.command_argv("sub-seek", &["1"])
     }
 
     /// See `SeekForward`.
     pub fn subtitle_seek_backward(&self) -> Result<()> {
-        self.command("sub-seek", &["-1"])
+        self.command_argv("sub-seek", &["-1"])
     }
 }
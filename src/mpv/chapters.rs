@@ -0,0 +1,144 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of libmpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+
+use std::convert::TryFrom;
+
+/// A chapter of the current file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Chapter {
+    pub title: Option<String>,
+    /// The start of the chapter in seconds.
+    pub time: f64,
+}
+
+/// An edition of the current file, e.g. a director's cut in a Matroska file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Edition {
+    pub id: i64,
+    pub title: Option<String>,
+    /// Whether this edition is played by default.
+    pub default: bool,
+}
+
+impl TryFrom<Node> for Chapter {
+    type Error = Error;
+
+    fn try_from(node: Node) -> Result<Chapter> {
+        Ok(Chapter {
+            title: node.get("title").and_then(Node::as_str).map(str::to_owned),
+            time: node
+                .get("time")
+                .and_then(Node::as_f64)
+                .ok_or(Error::Raw(mpv_error::PropertyFormat))?,
+        })
+    }
+}
+
+impl From<&Chapter> for Node {
+    fn from(chapter: &Chapter) -> Node {
+        let mut map = vec![("time".to_owned(), chapter.time.into())];
+        if let Some(title) = &chapter.title {
+            map.push(("title".to_owned(), title.as_str().into()));
+        }
+        Node::Map(map)
+    }
+}
+
+impl TryFrom<Node> for Edition {
+    type Error = Error;
+
+    fn try_from(node: Node) -> Result<Edition> {
+        Ok(Edition {
+            id: node
+                .get("id")
+                .and_then(Node::as_i64)
+                .ok_or(Error::Raw(mpv_error::PropertyFormat))?,
+            title: node.get("title").and_then(Node::as_str).map(str::to_owned),
+            default: node.get("default").and_then(Node::as_bool).unwrap_or(false),
+        })
+    }
+}
+
+impl Mpv {
+    /// The chapters of the current file, sorted by time.
+    pub fn chapters(&self) -> Result<Vec<Chapter>> {
+        self.get_node_property("chapter-list")
+    }
+
+    /// Replace the chapters of the current file, e.g. to add chapter markers to a file that has
+    /// none. This lasts until the next file is loaded.
+    pub fn set_chapters(&self, chapters: &[Chapter]) -> Result<()> {
+        let list = Node::Array(chapters.iter().map(Node::from).collect());
+        self.set_property("chapter-list", list)
+    }
+
+    /// The index of the current chapter. Returns `None` if there are no chapters, or the playback
+    /// position is before the first one.
+    pub fn current_chapter(&self) -> Result<Option<usize>> {
+        match self.get_property::<i64>("chapter") {
+            Ok(chapter) if chapter >= 0 => Ok(Some(chapter as usize)),
+            Ok(_) => Ok(None),
+            Err(e) if e.code() == Some(mpv_error::PropertyUnavailable) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Seek to the start of the chapter at `index`.
+    pub fn seek_chapter(&self, index: usize) -> Result<()> {
+        self.set_property("chapter", index as i64)
+    }
+
+    /// Seek to the start of the next chapter.
+    pub fn chapter_next(&self) -> Result<()> {
+        self.command_argv("add", &["chapter", "1"])
+    }
+
+    /// Seek to the start of the current chapter, or the previous one, like mpv's `PGUP` binding.
+    pub fn chapter_prev(&self) -> Result<()> {
+        self.command_argv("add", &["chapter", "-1"])
+    }
+
+    /// Watch the index of the current chapter. Like `current_chapter`, the watcher yields `None` if
+    /// there are no chapters, or the playback position is before the first one.
+    pub fn watch_chapter(&self) -> Result<PropertyWatcher<'_, usize>> {
+        self.watch_with("chapter", Format::Int64, |data| {
+            Ok(usize::try_from(i64::try_from(data)?).ok())
+        })
+    }
+
+    /// The editions of the current file.
+    pub fn editions(&self) -> Result<Vec<Edition>> {
+        self.get_node_property("edition-list")
+    }
+
+    /// The ID of the edition being played, or `None` if the file has no editions.
+    pub fn current_edition(&self) -> Result<Option<i64>> {
+        match self.get_property("current-edition") {
+            Ok(edition) => Ok(Some(edition)),
+            Err(e) if e.code() == Some(mpv_error::PropertyUnavailable) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Switch to the edition `id`, which reloads the current file.
+    pub fn set_edition(&self, id: i64) -> Result<()> {
+        self.set_property("edition", id)
+    }
+}
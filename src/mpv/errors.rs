@@ -16,29 +16,95 @@
 // License along with this library; if not, write to the Free Software
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 
-use std::{error, ffi::NulError, fmt, os::raw as ctype, rc::Rc, str::Utf8Error};
+use std::{error, ffi::NulError, fmt, os::raw as ctype, str::Utf8Error};
 
 #[allow(missing_docs)]
 pub type Result<T> = ::std::result::Result<T, Error>;
 
+/// The errors returned by this crate. `Error` is `Send + Sync`, and errors with context, such as
+/// `Property` or `Command`, report what went wrong via `source()`.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum Error {
-    Loadfiles {
-        index: usize,
-        error: Rc<Error>,
-    },
+    /// Loading the file at `index` by `Mpv::playlist_load_files` failed.
+    Loadfiles { index: usize, error: Box<Error> },
+    /// The loaded libmpv does not match the client API version this crate was built against.
     VersionMismatch {
         linked: ctype::c_ulong,
         loaded: ctype::c_ulong,
     },
+    /// mpv returned a string that is not valid UTF-8.
     InvalidUtf8,
+    /// A string passed to mpv contained a nul byte, or mpv returned a null pointer.
     Null,
+    /// An mpv API call failed with the given error code.
     Raw(crate::MpvError),
+    /// Getting, setting or observing the property `name` failed.
+    Property { name: String, error: Box<Error> },
+    /// Running the command given by `args`, starting with the command name, failed.
+    Command {
+        args: Vec<String>,
+        error: Box<Error>,
+    },
+    /// (De)serializing a `Node` failed.
+    Serde(String),
+}
+
+impl Error {
+    /// The mpv error code behind this error, looking through any context, if there is one.
+    ///
+    /// ```ignore
+    /// match mpv.get_property::<f64>("time-pos") {
+    ///     Err(e) if e.code() == Some(mpv_error::PropertyUnavailable) => println!("not playing"),
+    ///     ret => println!("{:?}", ret?),
+    /// }
+    /// ```
+    pub fn code(&self) -> Option<crate::MpvError> {
+        match self {
+            Error::Raw(code) => Some(*code),
+            Error::Loadfiles { error, .. }
+            | Error::Property { error, .. }
+            | Error::Command { error, .. } => error.code(),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn for_property(self, name: &str) -> Error {
+        Error::Property {
+            name: name.to_owned(),
+            error: Box::new(self),
+        }
+    }
+
+    pub(crate) fn for_command<S: AsRef<str>>(self, name: &str, args: &[S]) -> Error {
+        Error::Command {
+            args: ::std::iter::once(name)
+                .chain(args.iter().map(AsRef::as_ref))
+                .map(str::to_owned)
+                .collect(),
+            error: Box::new(self),
+        }
+    }
 }
 
 impl fmt::Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-        write!(f, "{:?}", self)
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Loadfiles { index, .. } => write!(f, "failed to load file {}", index),
+            Error::VersionMismatch { linked, loaded } => write!(
+                f,
+                "client API version mismatch: built against {}.{}, loaded {}.{}",
+                linked >> 16,
+                linked & 0xffff,
+                loaded >> 16,
+                loaded & 0xffff
+            ),
+            Error::InvalidUtf8 => f.write_str("mpv returned invalid UTF-8"),
+            Error::Null => f.write_str("unexpected nul byte or null pointer"),
+            Error::Raw(code) => write!(f, "{} ({})", libmpv_sys::mpv_error_str(*code), code),
+            Error::Property { name, .. } => write!(f, "property `{}` failed", name),
+            Error::Command { args, .. } => write!(f, "command `{}` failed", args.join(" ")),
+            Error::Serde(msg) => f.write_str(msg),
+        }
     }
 }
 
@@ -59,4 +125,13 @@ impl From<crate::MpvError> for Error {
     }
 }
 
-impl error::Error for Error {}
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Loadfiles { error, .. }
+            | Error::Property { error, .. }
+            | Error::Command { error, .. } => Some(&**error),
+            _ => None,
+        }
+    }
+}
@@ -20,33 +20,56 @@ use libmpv_sys::mpv_event;
 
 use crate::{mpv::mpv_err, *};
 
+use std::convert::TryFrom;
 use std::ffi::CString;
-use std::marker::PhantomData;
 use std::os::raw as ctype;
-use std::ptr::NonNull;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr::{self, NonNull};
 use std::slice;
 use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex, PoisonError};
+#[cfg(feature = "futures")]
+use {
+    futures_core::Stream,
+    std::pin::Pin,
+    std::task::{self, Poll, Waker},
+};
+#[cfg(unix)]
+use {
+    std::fs::File,
+    std::io::Read,
+    std::mem::ManuallyDrop,
+    std::os::unix::io::{FromRawFd, RawFd},
+};
 
 /// An `Event`'s ID.
 pub use libmpv_sys::mpv_event_id as EventId;
 pub mod mpv_event_id {
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_AUDIO_RECONFIG as AudioReconfig;
+    pub use libmpv_sys::mpv_event_id_MPV_EVENT_CHAPTER_CHANGE as ChapterChange;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_CLIENT_MESSAGE as ClientMessage;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_COMMAND_REPLY as CommandReply;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_END_FILE as EndFile;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_FILE_LOADED as FileLoaded;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_GET_PROPERTY_REPLY as GetPropertyReply;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_HOOK as Hook;
+    pub use libmpv_sys::mpv_event_id_MPV_EVENT_IDLE as Idle;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_LOG_MESSAGE as LogMessage;
+    pub use libmpv_sys::mpv_event_id_MPV_EVENT_METADATA_UPDATE as MetadataUpdate;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_NONE as None;
+    pub use libmpv_sys::mpv_event_id_MPV_EVENT_PAUSE as Pause;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_PLAYBACK_RESTART as PlaybackRestart;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_PROPERTY_CHANGE as PropertyChange;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_QUEUE_OVERFLOW as QueueOverflow;
+    pub use libmpv_sys::mpv_event_id_MPV_EVENT_SCRIPT_INPUT_DISPATCH as ScriptInputDispatch;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_SEEK as Seek;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_SET_PROPERTY_REPLY as SetPropertyReply;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_SHUTDOWN as Shutdown;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_START_FILE as StartFile;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_TICK as Tick;
+    pub use libmpv_sys::mpv_event_id_MPV_EVENT_TRACKS_CHANGED as TracksChanged;
+    pub use libmpv_sys::mpv_event_id_MPV_EVENT_TRACK_SWITCHED as TrackSwitched;
+    pub use libmpv_sys::mpv_event_id_MPV_EVENT_UNPAUSE as Unpause;
     pub use libmpv_sys::mpv_event_id_MPV_EVENT_VIDEO_RECONFIG as VideoReconfig;
 }
 
@@ -65,7 +88,8 @@ impl Mpv {
         } else {
             EventContext {
                 ctx: self.ctx,
-                _does_not_outlive: PhantomData::<&Self>,
+                mpv: self,
+                wakeup_callback: None,
             }
         }
     }
@@ -79,13 +103,21 @@ pub enum PropertyData<'a> {
     Flag(bool),
     Int64(i64),
     Double(ctype::c_double),
-    Node(&'a MpvNode),
+    Node(MpvNodeRef<'a>),
+    /// The property is not available, e.g. because the file it belongs to has ended.
+    Unavailable,
 }
 
 impl<'a> PropertyData<'a> {
     // SAFETY: meant to extract the data from an event property. See `mpv_event_property` in
     // `client.h`
-    unsafe fn from_raw(format: MpvFormat, ptr: *mut ctype::c_void) -> Result<PropertyData<'a>> {
+    pub(crate) unsafe fn from_raw(
+        format: MpvFormat,
+        ptr: *mut ctype::c_void,
+    ) -> Result<PropertyData<'a>> {
+        if format == mpv_format::None {
+            return Ok(PropertyData::Unavailable);
+        }
         assert!(!ptr.is_null());
         match format {
             mpv_format::Flag => Ok(PropertyData::Flag(*(ptr as *mut bool))),
@@ -99,18 +131,33 @@ impl<'a> PropertyData<'a> {
             }
             mpv_format::Double => Ok(PropertyData::Double(*(ptr as *mut f64))),
             mpv_format::Int64 => Ok(PropertyData::Int64(*(ptr as *mut i64))),
-            mpv_format::Node => Ok(PropertyData::Node(&*(ptr as *mut MpvNode))),
-            mpv_format::None => unreachable!(),
+            mpv_format::Node => Ok(PropertyData::Node(MpvNodeRef::from_raw(ptr as *const _))),
             _ => unimplemented!(),
         }
     }
+
+    /// Copy the data, see `Event::to_owned_event`.
+    pub fn to_owned_data(&self) -> Result<OwnedPropertyData> {
+        Ok(match *self {
+            PropertyData::Str(value) => OwnedPropertyData::Str(value.to_owned()),
+            PropertyData::OsdStr(value) => OwnedPropertyData::OsdStr(value.to_owned()),
+            PropertyData::Flag(value) => OwnedPropertyData::Flag(value),
+            PropertyData::Int64(value) => OwnedPropertyData::Int64(value),
+            PropertyData::Double(value) => OwnedPropertyData::Double(value),
+            PropertyData::Node(node) => OwnedPropertyData::Node(node.to_node()?),
+            PropertyData::Unavailable => OwnedPropertyData::Unavailable,
+        })
+    }
 }
 
 #[derive(Debug)]
 pub enum Event<'a> {
     /// Received when the player is shutting down
     Shutdown,
-    /// *Has not been tested*, received when explicitly asked to MPV
+    /// Received after requesting log messages with `EventContext::request_log_messages`.
+    ///
+    /// With the `log` or `tracing` feature enabled, these are also forwarded to the respective
+    /// crate, using `prefix` as the target (or as field, for `tracing`).
     LogMessage {
         prefix: &'a str,
         level: &'a str,
@@ -126,35 +173,535 @@ pub enum Event<'a> {
     /// Received when using set_property_async
     SetPropertyReply(u64),
     /// Received when using command_async
-    CommandReply(u64),
+    CommandReply {
+        reply_userdata: u64,
+        /// The result of the command, like `Mpv::command_ret` would have returned it.
+        result: MpvNodeRef<'a>,
+    },
     /// Event received when a new file is playing
-    StartFile,
+    StartFile {
+        /// The playlist entry that is being started.
+        playlist_entry_id: i64,
+    },
     /// Event received when the file being played currently has stopped, for an error or not
-    EndFile(EndFileReason),
+    EndFile {
+        reason: EndFileReason,
+        /// Set if `reason` is `mpv_end_file_reason::Error`.
+        error: Option<MpvError>,
+        /// The playlist entry that has ended.
+        playlist_entry_id: i64,
+        /// If `reason` is `mpv_end_file_reason::Redirect`, the id of the first entry that was
+        /// inserted into the playlist in its place, or `0`.
+        playlist_insert_id: i64,
+        /// The number of entries inserted by a redirect.
+        playlist_insert_num_entries: i32,
+    },
     /// Event received when a file has been *loaded*, but has not been started
     FileLoaded,
+    /// Deprecated, observe the `track-list` property instead
+    TracksChanged,
+    /// Deprecated, observe the `vid`, `aid` and `sid` properties instead
+    TrackSwitched,
+    /// Deprecated, observe the `idle-active` property instead
+    Idle,
+    /// Deprecated, observe the `pause` property instead
+    Pause,
+    /// Deprecated, observe the `pause` property instead
+    Unpause,
+    /// Deprecated, sent every time a video frame is displayed
+    Tick,
+    /// Deprecated, no longer sent by mpv
+    ScriptInputDispatch {
+        arg0: i32,
+        kind: &'a str,
+    },
+    /// Received by `script-message` or `script-message-to`, after it was passed to the handler
+    /// registered by `Mpv::register_message_handler`, if any
     ClientMessage(Vec<&'a str>),
     VideoReconfig,
     AudioReconfig,
     /// The player changed current position
     Seek,
     PlaybackRestart,
-    /// Received when used with observe_property
+    /// Received when used with observe_property. If the property became unavailable, e.g. when
+    /// the file it belongs to ended, `change` is `PropertyData::Unavailable`.
     PropertyChange {
         name: &'a str,
         change: PropertyData<'a>,
         reply_userdata: u64,
     },
+    /// Deprecated, observe the `metadata` property instead
+    MetadataUpdate,
+    /// Deprecated, observe the `chapter` property instead
+    ChapterChange,
     /// Received when the Event Queue is full
     QueueOverflow,
-    /// A deprecated event
-    Deprecated(mpv_event),
+    /// Received when a hook registered with `EventContext::hook_add` is run. The player is
+    /// blocked until `guard` is dropped.
+    Hook {
+        name: &'a str,
+        reply_userdata: u64,
+        guard: HookGuard,
+    },
+    /// An event that was added in a newer version of mpv than this crate knows about
+    Unknown(EventId),
+}
+
+impl<'a> Event<'a> {
+    /// Copy all data of this event, so that it can outlive the next call to
+    /// `EventContext::wait_event` and be sent to other threads.
+    ///
+    /// The current `Mpv::get_internal_time` of `mpv` is attached as timestamp, which is why a
+    /// handle is needed: mpv's clock can only be read through one. The `HookGuard` of an
+    /// `Event::Hook` is not copied, the hook continues once this event is dropped.
+    pub fn to_owned_event(&self, mpv: &Mpv) -> Result<OwnedEvent> {
+        Ok(OwnedEvent {
+            time_us: mpv.get_internal_time(),
+            kind: self.to_owned_kind()?,
+        })
+    }
+
+    /// Convert this event to the node representation that is also used by `mpv_event_to_node`
+    /// and mpv's JSON IPC, see `OwnedEvent::to_node`.
+    pub fn to_node(&self) -> Result<Node> {
+        Ok(self.to_owned_kind()?.to_node())
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// Like `to_node`, but rendered as JSON.
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        Ok(self.to_node()?.to_json())
+    }
+
+    fn to_owned_kind(&self) -> Result<OwnedEventKind> {
+        Ok(match self {
+            Event::Shutdown => OwnedEventKind::Shutdown,
+            Event::LogMessage {
+                prefix,
+                level,
+                text,
+                log_level,
+            } => OwnedEventKind::LogMessage {
+                prefix: prefix.to_string(),
+                level: level.to_string(),
+                text: text.to_string(),
+                log_level: *log_level,
+            },
+            Event::GetPropertyReply {
+                name,
+                result,
+                reply_userdata,
+            } => OwnedEventKind::GetPropertyReply {
+                name: name.to_string(),
+                result: result.to_owned_data()?,
+                reply_userdata: *reply_userdata,
+            },
+            Event::SetPropertyReply(id) => OwnedEventKind::SetPropertyReply(*id),
+            Event::CommandReply {
+                reply_userdata,
+                result,
+            } => OwnedEventKind::CommandReply {
+                reply_userdata: *reply_userdata,
+                result: result.to_node()?,
+            },
+            Event::StartFile { playlist_entry_id } => OwnedEventKind::StartFile {
+                playlist_entry_id: *playlist_entry_id,
+            },
+            Event::EndFile {
+                reason,
+                error,
+                playlist_entry_id,
+                playlist_insert_id,
+                playlist_insert_num_entries,
+            } => OwnedEventKind::EndFile {
+                reason: *reason,
+                error: *error,
+                playlist_entry_id: *playlist_entry_id,
+                playlist_insert_id: *playlist_insert_id,
+                playlist_insert_num_entries: *playlist_insert_num_entries,
+            },
+            Event::FileLoaded => OwnedEventKind::FileLoaded,
+            Event::TracksChanged => OwnedEventKind::TracksChanged,
+            Event::TrackSwitched => OwnedEventKind::TrackSwitched,
+            Event::Idle => OwnedEventKind::Idle,
+            Event::Pause => OwnedEventKind::Pause,
+            Event::Unpause => OwnedEventKind::Unpause,
+            Event::Tick => OwnedEventKind::Tick,
+            Event::ScriptInputDispatch { arg0, kind } => OwnedEventKind::ScriptInputDispatch {
+                arg0: *arg0,
+                kind: kind.to_string(),
+            },
+            Event::ClientMessage(args) => {
+                OwnedEventKind::ClientMessage(args.iter().map(|arg| arg.to_string()).collect())
+            }
+            Event::VideoReconfig => OwnedEventKind::VideoReconfig,
+            Event::AudioReconfig => OwnedEventKind::AudioReconfig,
+            Event::Seek => OwnedEventKind::Seek,
+            Event::PlaybackRestart => OwnedEventKind::PlaybackRestart,
+            Event::PropertyChange {
+                name,
+                change,
+                reply_userdata,
+            } => OwnedEventKind::PropertyChange {
+                name: name.to_string(),
+                change: change.to_owned_data()?,
+                reply_userdata: *reply_userdata,
+            },
+            Event::MetadataUpdate => OwnedEventKind::MetadataUpdate,
+            Event::ChapterChange => OwnedEventKind::ChapterChange,
+            Event::QueueOverflow => OwnedEventKind::QueueOverflow,
+            Event::Hook {
+                name,
+                reply_userdata,
+                guard,
+            } => OwnedEventKind::Hook {
+                name: name.to_string(),
+                reply_userdata: *reply_userdata,
+                hook_id: guard.id,
+            },
+            Event::Unknown(id) => OwnedEventKind::Unknown(*id),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An owned copy of `PropertyData`.
+pub enum OwnedPropertyData {
+    Str(String),
+    OsdStr(String),
+    Flag(bool),
+    Int64(i64),
+    Double(ctype::c_double),
+    Node(Node),
+    Unavailable,
+}
+
+impl TryFrom<OwnedPropertyData> for bool {
+    type Error = Error;
+
+    fn try_from(data: OwnedPropertyData) -> Result<bool> {
+        match data {
+            OwnedPropertyData::Flag(value) => Ok(value),
+            _ => Err(Error::Raw(mpv_error::PropertyFormat)),
+        }
+    }
+}
+
+impl TryFrom<OwnedPropertyData> for i64 {
+    type Error = Error;
+
+    fn try_from(data: OwnedPropertyData) -> Result<i64> {
+        match data {
+            OwnedPropertyData::Int64(value) => Ok(value),
+            _ => Err(Error::Raw(mpv_error::PropertyFormat)),
+        }
+    }
+}
+
+impl TryFrom<OwnedPropertyData> for f64 {
+    type Error = Error;
+
+    fn try_from(data: OwnedPropertyData) -> Result<f64> {
+        match data {
+            OwnedPropertyData::Double(value) => Ok(value),
+            _ => Err(Error::Raw(mpv_error::PropertyFormat)),
+        }
+    }
+}
+
+impl TryFrom<OwnedPropertyData> for String {
+    type Error = Error;
+
+    fn try_from(data: OwnedPropertyData) -> Result<String> {
+        match data {
+            OwnedPropertyData::Str(value) | OwnedPropertyData::OsdStr(value) => Ok(value),
+            _ => Err(Error::Raw(mpv_error::PropertyFormat)),
+        }
+    }
+}
+
+impl TryFrom<OwnedPropertyData> for Node {
+    type Error = Error;
+
+    fn try_from(data: OwnedPropertyData) -> Result<Node> {
+        Ok(match data {
+            OwnedPropertyData::Str(value) | OwnedPropertyData::OsdStr(value) => Node::String(value),
+            OwnedPropertyData::Flag(value) => Node::Flag(value),
+            OwnedPropertyData::Int64(value) => Node::Int64(value),
+            OwnedPropertyData::Double(value) => Node::Double(value),
+            OwnedPropertyData::Node(node) => node,
+            OwnedPropertyData::Unavailable => Node::None,
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// An `Event` that owns its data, created by `Event::to_owned_event`.
+pub struct OwnedEvent {
+    /// `Mpv::get_internal_time` at the time the event was copied.
+    pub time_us: i64,
+    pub kind: OwnedEventKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+/// The owned counterpart of every `Event` variant, see there.
+pub enum OwnedEventKind {
+    Shutdown,
+    LogMessage {
+        prefix: String,
+        level: String,
+        text: String,
+        log_level: LogLevel,
+    },
+    GetPropertyReply {
+        name: String,
+        result: OwnedPropertyData,
+        reply_userdata: u64,
+    },
+    SetPropertyReply(u64),
+    CommandReply {
+        reply_userdata: u64,
+        result: Node,
+    },
+    StartFile {
+        playlist_entry_id: i64,
+    },
+    EndFile {
+        reason: EndFileReason,
+        error: Option<MpvError>,
+        playlist_entry_id: i64,
+        playlist_insert_id: i64,
+        playlist_insert_num_entries: i32,
+    },
+    FileLoaded,
+    TracksChanged,
+    TrackSwitched,
+    Idle,
+    Pause,
+    Unpause,
+    Tick,
+    ScriptInputDispatch {
+        arg0: i32,
+        kind: String,
+    },
+    ClientMessage(Vec<String>),
+    VideoReconfig,
+    AudioReconfig,
+    Seek,
+    PlaybackRestart,
+    PropertyChange {
+        name: String,
+        change: OwnedPropertyData,
+        reply_userdata: u64,
+    },
+    MetadataUpdate,
+    ChapterChange,
+    QueueOverflow,
+    Hook {
+        name: String,
+        reply_userdata: u64,
+        /// The ID mpv uses to continue the hook.
+        hook_id: u64,
+    },
+    Unknown(EventId),
+}
+
+impl OwnedEvent {
+    /// Convert this event to the node representation that is also used by `mpv_event_to_node`
+    /// and mpv's JSON IPC, e.g. `{"event": "property-change", "id": 1, "name": "volume",
+    /// "data": 100}`. The timestamp is not part of it.
+    pub fn to_node(&self) -> Node {
+        self.kind.to_node()
+    }
+
+    #[cfg(feature = "serde_json")]
+    /// Like `to_node`, but rendered as JSON.
+    pub fn to_json(&self) -> serde_json::Value {
+        self.to_node().to_json()
+    }
+}
+
+impl OwnedEventKind {
+    fn event_id(&self) -> EventId {
+        match self {
+            OwnedEventKind::Shutdown => mpv_event_id::Shutdown,
+            OwnedEventKind::LogMessage { .. } => mpv_event_id::LogMessage,
+            OwnedEventKind::GetPropertyReply { .. } => mpv_event_id::GetPropertyReply,
+            OwnedEventKind::SetPropertyReply(_) => mpv_event_id::SetPropertyReply,
+            OwnedEventKind::CommandReply { .. } => mpv_event_id::CommandReply,
+            OwnedEventKind::StartFile { .. } => mpv_event_id::StartFile,
+            OwnedEventKind::EndFile { .. } => mpv_event_id::EndFile,
+            OwnedEventKind::FileLoaded => mpv_event_id::FileLoaded,
+            OwnedEventKind::TracksChanged => mpv_event_id::TracksChanged,
+            OwnedEventKind::TrackSwitched => mpv_event_id::TrackSwitched,
+            OwnedEventKind::Idle => mpv_event_id::Idle,
+            OwnedEventKind::Pause => mpv_event_id::Pause,
+            OwnedEventKind::Unpause => mpv_event_id::Unpause,
+            OwnedEventKind::Tick => mpv_event_id::Tick,
+            OwnedEventKind::ScriptInputDispatch { .. } => mpv_event_id::ScriptInputDispatch,
+            OwnedEventKind::ClientMessage(_) => mpv_event_id::ClientMessage,
+            OwnedEventKind::VideoReconfig => mpv_event_id::VideoReconfig,
+            OwnedEventKind::AudioReconfig => mpv_event_id::AudioReconfig,
+            OwnedEventKind::Seek => mpv_event_id::Seek,
+            OwnedEventKind::PlaybackRestart => mpv_event_id::PlaybackRestart,
+            OwnedEventKind::PropertyChange { .. } => mpv_event_id::PropertyChange,
+            OwnedEventKind::MetadataUpdate => mpv_event_id::MetadataUpdate,
+            OwnedEventKind::ChapterChange => mpv_event_id::ChapterChange,
+            OwnedEventKind::QueueOverflow => mpv_event_id::QueueOverflow,
+            OwnedEventKind::Hook { .. } => mpv_event_id::Hook,
+            OwnedEventKind::Unknown(id) => *id,
+        }
+    }
+
+    // Mirrors `mpv_event_to_node` in mpv's `player/client.c`.
+    fn to_node(&self) -> Node {
+        let event = unsafe { mpv_cstr_to_str!(libmpv_sys::mpv_event_name(self.event_id())) };
+        let mut map = vec![("event".to_owned(), Node::from(event.unwrap_or("unknown")))];
+        let mut add = |key: &str, value: Node| map.push((key.to_owned(), value));
+
+        let reply_userdata = match *self {
+            OwnedEventKind::GetPropertyReply { reply_userdata, .. }
+            | OwnedEventKind::SetPropertyReply(reply_userdata)
+            | OwnedEventKind::CommandReply { reply_userdata, .. }
+            | OwnedEventKind::PropertyChange { reply_userdata, .. }
+            | OwnedEventKind::Hook { reply_userdata, .. } => reply_userdata,
+            _ => 0,
+        };
+        if reply_userdata != 0 {
+            add("id", Node::Int64(reply_userdata as i64));
+        }
+
+        match self {
+            OwnedEventKind::LogMessage {
+                prefix,
+                level,
+                text,
+                ..
+            } => {
+                add("prefix", prefix.as_str().into());
+                add("level", level.as_str().into());
+                add("text", text.as_str().into());
+            }
+            OwnedEventKind::GetPropertyReply {
+                name, result: data, ..
+            }
+            | OwnedEventKind::PropertyChange {
+                name, change: data, ..
+            } => {
+                add("name", name.as_str().into());
+                add("data", Node::try_from(data.clone()).unwrap_or(Node::None));
+            }
+            OwnedEventKind::CommandReply { result, .. } => add("result", result.clone()),
+            OwnedEventKind::StartFile { playlist_entry_id } => {
+                add("playlist_entry_id", (*playlist_entry_id).into());
+            }
+            OwnedEventKind::EndFile {
+                reason,
+                error,
+                playlist_entry_id,
+                playlist_insert_id,
+                playlist_insert_num_entries,
+            } => {
+                let reason_name = match *reason {
+                    mpv_end_file_reason::Eof => "eof",
+                    mpv_end_file_reason::Stop => "stop",
+                    mpv_end_file_reason::Quit => "quit",
+                    mpv_end_file_reason::Error => "error",
+                    mpv_end_file_reason::Redirect => "redirect",
+                    _ => "unknown",
+                };
+                add("reason", reason_name.into());
+                add("playlist_entry_id", (*playlist_entry_id).into());
+                if *playlist_insert_id != 0 {
+                    add("playlist_insert_id", (*playlist_insert_id).into());
+                    add(
+                        "playlist_insert_num_entries",
+                        i64::from(*playlist_insert_num_entries).into(),
+                    );
+                }
+                if *reason == mpv_end_file_reason::Error {
+                    let error = error.unwrap_or(mpv_error::Generic);
+                    let error = unsafe { mpv_cstr_to_str!(libmpv_sys::mpv_error_string(error)) };
+                    add("file_error", error.unwrap_or("unknown").into());
+                }
+            }
+            OwnedEventKind::ClientMessage(args) => add("args", args.clone().into()),
+            OwnedEventKind::Hook { hook_id, .. } => add("hook_id", (*hook_id as i64).into()),
+            _ => {}
+        }
+
+        Node::Map(map)
+    }
+}
+
+/// The handle that hooks are continued on. It is cleared before its `Mpv` is destroyed, so that
+/// a `HookGuard` may outlive both its event and its `Mpv`.
+#[derive(Debug)]
+pub(crate) struct HookHandle(Mutex<Option<NonNull<libmpv_sys::mpv_handle>>>);
+
+unsafe impl Send for HookHandle {}
+unsafe impl Sync for HookHandle {}
+
+impl HookHandle {
+    pub(crate) fn new(ctx: NonNull<libmpv_sys::mpv_handle>) -> Arc<HookHandle> {
+        Arc::new(HookHandle(Mutex::new(Some(ctx))))
+    }
+
+    /// Forget the handle, which blocks until no guard is continuing its hook.
+    pub(crate) fn clear(&self) {
+        *self.0.lock().unwrap_or_else(PoisonError::into_inner) = None;
+    }
+}
+
+/// Lets the player continue after a hook, once it is dropped or `resume` is called.
+///
+/// The guard does not borrow its event, so it can be kept while waiting for more events, e.g. for
+/// the reply of an asynchronous command. If the `Mpv` is dropped first, dropping the guard does
+/// nothing. Since this also happens during unwinding, a panicking hook handler does not block
+/// the player indefinitely.
+#[derive(Debug)]
+pub struct HookGuard {
+    handle: Arc<HookHandle>,
+    id: u64,
+}
+
+impl HookGuard {
+    /// Let the player continue now.
+    pub fn resume(self) {}
+}
+
+impl Drop for HookGuard {
+    fn drop(&mut self) {
+        let ctx = self.handle.0.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(ctx) = *ctx {
+            unsafe { libmpv_sys::mpv_hook_continue(ctx.as_ptr(), self.id) };
+        }
+    }
 }
 
 /// Context to listen to events.
 pub struct EventContext<'parent> {
     ctx: NonNull<libmpv_sys::mpv_handle>,
-    _does_not_outlive: PhantomData<&'parent Mpv>,
+    mpv: &'parent Mpv,
+    wakeup_callback: Option<Box<WakeupCallback>>,
+}
+
+type WakeupCallback = Box<dyn Fn() + Send + Sync>;
+
+unsafe extern "C" fn wakeup_wrapper(callback: *mut ctype::c_void) {
+    let callback = &*(callback as *const WakeupCallback);
+    // Unwinding into mpv is undefined behaviour.
+    let _ = panic::catch_unwind(AssertUnwindSafe(callback));
+}
+
+impl<'parent> Drop for EventContext<'parent> {
+    fn drop(&mut self) {
+        if self.wakeup_callback.is_some() {
+            unsafe {
+                libmpv_sys::mpv_set_wakeup_callback(self.ctx.as_ptr(), None, ptr::null_mut())
+            };
+        }
+    }
 }
 
 unsafe impl<'parent> Send for EventContext<'parent> {}
@@ -189,14 +736,14 @@ impl<'parent> EventContext<'parent> {
 
     /// Diable all deprecated events.
     pub fn disable_deprecated_events(&self) -> Result<()> {
-        self.disable_event(libmpv_sys::mpv_event_id_MPV_EVENT_TRACKS_CHANGED)?;
-        self.disable_event(libmpv_sys::mpv_event_id_MPV_EVENT_TRACK_SWITCHED)?;
-        self.disable_event(libmpv_sys::mpv_event_id_MPV_EVENT_IDLE)?;
-        self.disable_event(libmpv_sys::mpv_event_id_MPV_EVENT_PAUSE)?;
-        self.disable_event(libmpv_sys::mpv_event_id_MPV_EVENT_UNPAUSE)?;
-        self.disable_event(libmpv_sys::mpv_event_id_MPV_EVENT_SCRIPT_INPUT_DISPATCH)?;
-        self.disable_event(libmpv_sys::mpv_event_id_MPV_EVENT_METADATA_UPDATE)?;
-        self.disable_event(libmpv_sys::mpv_event_id_MPV_EVENT_CHAPTER_CHANGE)?;
+        self.disable_event(mpv_event_id::TracksChanged)?;
+        self.disable_event(mpv_event_id::TrackSwitched)?;
+        self.disable_event(mpv_event_id::Idle)?;
+        self.disable_event(mpv_event_id::Pause)?;
+        self.disable_event(mpv_event_id::Unpause)?;
+        self.disable_event(mpv_event_id::ScriptInputDispatch)?;
+        self.disable_event(mpv_event_id::MetadataUpdate)?;
+        self.disable_event(mpv_event_id::ChapterChange)?;
         Ok(())
     }
 
@@ -222,6 +769,38 @@ impl<'parent> EventContext<'parent> {
         })
     }
 
+    /// Receive log messages of at least the given verbosity as `Event::LogMessage`.
+    /// `mpv_log_level::None` disables them again.
+    pub fn request_log_messages(&self, level: LogLevel) -> Result<()> {
+        let level = match level {
+            mpv_log_level::Fatal => "fatal",
+            mpv_log_level::Error => "error",
+            mpv_log_level::Warn => "warn",
+            mpv_log_level::Info => "info",
+            mpv_log_level::V => "v",
+            mpv_log_level::Debug => "debug",
+            mpv_log_level::Trace => "trace",
+            _ => "no",
+        };
+        let level = CString::new(level)?;
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_request_log_messages(self.ctx.as_ptr(), level.as_ptr())
+        })
+    }
+
+    /// Register a hook handler for the hook `name`, e.g. `on_load`, `on_preloaded` or
+    /// `on_unload`. See the [manual](https://mpv.io/manual/master/#hooks) for all hooks.
+    ///
+    /// Whenever the hook is run, `Event::Hook` with `id` as `reply_userdata` is received, and the
+    /// player waits until its guard is dropped. Handlers with lower `priority` run first, use `0`
+    /// as a neutral default. Hooks are removed when the `Mpv` handle is destroyed.
+    pub fn hook_add(&self, name: &str, priority: i32, id: u64) -> Result<()> {
+        let name = CString::new(name)?;
+        mpv_err((), unsafe {
+            libmpv_sys::mpv_hook_add(self.ctx.as_ptr(), id, name.as_ptr(), priority)
+        })
+    }
+
     /// Unobserve any property associated with `id`.
     pub fn unobserve_property(&self, id: u64) -> Result<()> {
         mpv_err((), unsafe {
@@ -236,9 +815,105 @@ impl<'parent> EventContext<'parent> {
     ///
     /// Returns `Some(Err(...))` if there was invalid utf-8, or if either an
     /// `MPV_EVENT_GET_PROPERTY_REPLY`, `MPV_EVENT_SET_PROPERTY_REPLY`, `MPV_EVENT_COMMAND_REPLY`,
-    /// or `MPV_EVENT_PROPERTY_CHANGE` event failed, or if `MPV_EVENT_END_FILE` reported an error.
+    /// or `MPV_EVENT_PROPERTY_CHANGE` event failed. Errors of `MPV_EVENT_END_FILE` are reported
+    /// in `Event::EndFile` instead.
     pub fn wait_event(&mut self, timeout: f64) -> Option<Result<Event>> {
         let event = unsafe { *libmpv_sys::mpv_wait_event(self.ctx.as_ptr(), timeout) };
+        self.process_event(event)
+    }
+
+    /// Set a callback that mpv calls whenever new events may be available, replacing any
+    /// previously set callback. After a wakeup, `wait_event(0.)` should be called until it
+    /// returns `None`, since a single wakeup may stand for multiple events.
+    ///
+    /// The callback is called from arbitrary mpv threads, possibly while locks are held. It must
+    /// return quickly, and must not call any mpv API.
+    pub fn set_wakeup_callback<F: Fn() + Send + Sync + 'static>(&mut self, callback: F) {
+        let callback: Box<WakeupCallback> = Box::new(Box::new(callback));
+        unsafe {
+            libmpv_sys::mpv_set_wakeup_callback(
+                self.ctx.as_ptr(),
+                Some(wakeup_wrapper),
+                &*callback as *const WakeupCallback as *mut _,
+            )
+        };
+        // mpv won't call the old callback after `mpv_set_wakeup_callback` returned.
+        self.wakeup_callback = Some(callback);
+    }
+
+    /// The read end of mpv's wakeup pipe, which becomes readable whenever new events may be
+    /// available, e.g. to register it with `mio::unix::SourceFd`. Use `drain_events` to handle
+    /// them once it does. The pipe is created by the first call, and closed when `Mpv` is dropped.
+    #[cfg(unix)]
+    pub fn wakeup_pipe(&self) -> Result<RawFd> {
+        let fd = unsafe { libmpv_sys::mpv_get_wakeup_pipe(self.ctx.as_ptr()) };
+        if fd < 0 {
+            Err(Error::Raw(mpv_error::Generic))
+        } else {
+            Ok(fd)
+        }
+    }
+
+    /// Empty the wakeup pipe, then pass every pending event to `handler`, without blocking.
+    /// This is meant to be called whenever the wakeup pipe becomes readable.
+    #[cfg(unix)]
+    pub fn drain_events<F: FnMut(Result<Event<'_>>)>(&mut self, mut handler: F) -> Result<()> {
+        // Draining the pipe before polling for events ensures that no wakeup is missed.
+        let pipe = ManuallyDrop::new(unsafe { File::from_raw_fd(self.wakeup_pipe()?) });
+        let mut buf = [0; 256];
+        while let Ok(n) = (&*pipe).read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+        }
+
+        loop {
+            let event = unsafe { *libmpv_sys::mpv_wait_event(self.ctx.as_ptr(), 0.) };
+            if event.event_id == mpv_event_id::None {
+                return Ok(());
+            }
+            if let Some(event) = self.process_event(event) {
+                handler(event);
+            }
+        }
+    }
+
+    /// Turn this context into a `futures::Stream` of owned events, which is woken up by mpv's
+    /// wakeup callback and polls for events without blocking. This replaces any wakeup callback
+    /// set before. The stream ends after `Event::Shutdown`.
+    ///
+    /// Every event is copied with `Event::to_owned_event`, so hooks continue as soon as their
+    /// event is yielded. Use `into_stream_with` to handle hooks, or to avoid the copies.
+    #[cfg(feature = "futures")]
+    pub fn into_stream(self) -> EventStream<'parent> {
+        EventStream {
+            state: StreamState::new(self),
+        }
+    }
+
+    /// Like `into_stream`, but every event is passed to `map` while it still borrows from mpv's
+    /// event buffer, and the stream yields whatever it returns, skipping `None`s.
+    #[cfg(feature = "futures")]
+    pub fn into_stream_with<T, F>(self, map: F) -> MappedEventStream<'parent, F>
+    where
+        F: for<'a> FnMut(Result<Event<'a>>) -> Option<T> + Unpin,
+    {
+        MappedEventStream {
+            state: StreamState::new(self),
+            map,
+        }
+    }
+
+    fn process_event(&mut self, event: mpv_event) -> Option<Result<Event<'_>>> {
+        if event.event_id == mpv_event_id::CommandReply
+            || event.event_id == mpv_event_id::GetPropertyReply
+            || event.event_id == mpv_event_id::SetPropertyReply
+        {
+            self.mpv.replies.resolve(&event);
+        }
+        if event.event_id == mpv_event_id::PropertyChange {
+            self.mpv.watchers.dispatch(&event);
+        }
         if event.event_id != mpv_event_id::None {
             if let Err(e) = mpv_err((), event.error) {
                 return Some(Err(e));
@@ -254,10 +929,13 @@ impl<'parent> EventContext<'parent> {
 
                 let prefix = unsafe { mpv_cstr_to_str!(log_message.prefix) };
                 Some(prefix.and_then(|prefix| {
+                    let text = unsafe { mpv_cstr_to_str!(log_message.text)? };
+                    forward_log_message(prefix, text, log_message.log_level);
+
                     Ok(Event::LogMessage {
                         prefix,
                         level: unsafe { mpv_cstr_to_str!(log_message.level)? },
-                        text: unsafe { mpv_cstr_to_str!(log_message.text)? },
+                        text,
                         log_level: log_message.log_level,
                     })
                 }))
@@ -281,36 +959,67 @@ impl<'parent> EventContext<'parent> {
                 Event::SetPropertyReply(event.reply_userdata),
                 event.error,
             )),
-            mpv_event_id::CommandReply => Some(mpv_err(
-                Event::CommandReply(event.reply_userdata),
-                event.error,
-            )),
-            mpv_event_id::StartFile => Some(Ok(Event::StartFile)),
+            mpv_event_id::CommandReply => {
+                let command = event.data as *const libmpv_sys::mpv_event_command;
+                Some(Ok(Event::CommandReply {
+                    reply_userdata: event.reply_userdata,
+                    result: unsafe { MpvNodeRef::from_raw(&(*command).result) },
+                }))
+            }
+            mpv_event_id::StartFile => {
+                let start_file = unsafe { *(event.data as *mut libmpv_sys::mpv_event_start_file) };
+                Some(Ok(Event::StartFile {
+                    playlist_entry_id: start_file.playlist_entry_id,
+                }))
+            }
             mpv_event_id::EndFile => {
                 let end_file = unsafe { *(event.data as *mut libmpv_sys::mpv_event_end_file) };
-
-                if let Err(e) = mpv_err((), end_file.error) {
-                    Some(Err(e))
-                } else if end_file.reason.is_positive() {
-                    Some(Ok(Event::EndFile(end_file.reason as _)))
-                } else {
-                    None
-                }
+                Some(Ok(Event::EndFile {
+                    reason: end_file.reason as _,
+                    error: if end_file.error < 0 {
+                        Some(end_file.error)
+                    } else {
+                        None
+                    },
+                    playlist_entry_id: end_file.playlist_entry_id,
+                    playlist_insert_id: end_file.playlist_insert_id,
+                    playlist_insert_num_entries: end_file.playlist_insert_num_entries,
+                }))
             }
             mpv_event_id::FileLoaded => Some(Ok(Event::FileLoaded)),
+            mpv_event_id::TracksChanged => Some(Ok(Event::TracksChanged)),
+            mpv_event_id::TrackSwitched => Some(Ok(Event::TrackSwitched)),
+            mpv_event_id::Idle => Some(Ok(Event::Idle)),
+            mpv_event_id::Pause => Some(Ok(Event::Pause)),
+            mpv_event_id::Unpause => Some(Ok(Event::Unpause)),
+            mpv_event_id::Tick => Some(Ok(Event::Tick)),
+            mpv_event_id::ScriptInputDispatch => {
+                if event.data.is_null() {
+                    return Some(Err(Error::Null));
+                }
+                let dispatch =
+                    unsafe { *(event.data as *mut libmpv_sys::mpv_event_script_input_dispatch) };
+                let kind = unsafe { mpv_cstr_to_str!(dispatch.type_) };
+                Some(kind.map(|kind| Event::ScriptInputDispatch {
+                    arg0: dispatch.arg0,
+                    kind,
+                }))
+            }
             mpv_event_id::ClientMessage => {
                 let client_message =
                     unsafe { *(event.data as *mut libmpv_sys::mpv_event_client_message) };
                 let messages = unsafe {
                     slice::from_raw_parts_mut(client_message.args, client_message.num_args as _)
                 };
-                Some(Ok(Event::ClientMessage(
-                    messages
-                        .iter()
-                        .map(|msg| unsafe { mpv_cstr_to_str!(*msg) })
-                        .collect::<Result<Vec<_>>>()
-                        .unwrap(),
-                )))
+                let messages = messages
+                    .iter()
+                    .map(|msg| unsafe { mpv_cstr_to_str!(*msg) })
+                    .collect::<Result<Vec<_>>>();
+                if let Ok(messages) = &messages {
+                    self.mpv.key_bindings.dispatch(messages);
+                    self.mpv.messages.dispatch(messages);
+                }
+                Some(messages.map(Event::ClientMessage))
             }
             mpv_event_id::VideoReconfig => Some(Ok(Event::VideoReconfig)),
             mpv_event_id::AudioReconfig => Some(Ok(Event::AudioReconfig)),
@@ -319,27 +1028,164 @@ impl<'parent> EventContext<'parent> {
             mpv_event_id::PropertyChange => {
                 let property = unsafe { *(event.data as *mut libmpv_sys::mpv_event_property) };
 
-                // This happens if the property is not available. For example,
-                // if you reached EndFile while observing a property.
-                if property.format == mpv_format::None {
-                    None
-                } else {
-                    let name = unsafe { mpv_cstr_to_str!(property.name) };
-                    Some(name.and_then(|name| {
-                        // SAFETY: safe because we are passing format + data from an mpv_event_property
-                        let change =
-                            unsafe { PropertyData::from_raw(property.format, property.data) }?;
-
-                        Ok(Event::PropertyChange {
-                            name,
-                            change,
-                            reply_userdata: event.reply_userdata,
-                        })
-                    }))
-                }
+                let name = unsafe { mpv_cstr_to_str!(property.name) };
+                Some(name.and_then(|name| {
+                    // SAFETY: safe because we are passing format + data from an mpv_event_property
+                    let change = unsafe { PropertyData::from_raw(property.format, property.data) }?;
+
+                    Ok(Event::PropertyChange {
+                        name,
+                        change,
+                        reply_userdata: event.reply_userdata,
+                    })
+                }))
             }
+            mpv_event_id::MetadataUpdate => Some(Ok(Event::MetadataUpdate)),
+            mpv_event_id::ChapterChange => Some(Ok(Event::ChapterChange)),
             mpv_event_id::QueueOverflow => Some(Ok(Event::QueueOverflow)),
-            _ => Some(Ok(Event::Deprecated(event))),
+            mpv_event_id::Hook => {
+                let hook = unsafe { *(event.data as *mut libmpv_sys::mpv_event_hook) };
+                // Create the guard first, so that the hook is continued even if the name is
+                // invalid.
+                let guard = HookGuard {
+                    handle: self.mpv.hook_handle.clone(),
+                    id: hook.id,
+                };
+
+                let name = unsafe { mpv_cstr_to_str!(hook.name) };
+                Some(name.map(|name| Event::Hook {
+                    name,
+                    reply_userdata: event.reply_userdata,
+                    guard,
+                }))
+            }
+            id => Some(Ok(Event::Unknown(id))),
+        }
+    }
+}
+
+#[allow(unused_variables)]
+fn forward_log_message(prefix: &str, text: &str, level: LogLevel) {
+    let text = text.trim_end();
+
+    #[cfg(feature = "log")]
+    {
+        let level = match level {
+            mpv_log_level::Fatal | mpv_log_level::Error => log::Level::Error,
+            mpv_log_level::Warn => log::Level::Warn,
+            mpv_log_level::Info => log::Level::Info,
+            mpv_log_level::V | mpv_log_level::Debug => log::Level::Debug,
+            _ => log::Level::Trace,
+        };
+        log::log!(target: prefix, level, "{}", text);
+    }
+
+    #[cfg(feature = "tracing")]
+    {
+        // `tracing` requires the target and level to be known at compile time.
+        match level {
+            mpv_log_level::Fatal | mpv_log_level::Error => {
+                tracing::error!(target: "mpv", prefix, "{}", text)
+            }
+            mpv_log_level::Warn => tracing::warn!(target: "mpv", prefix, "{}", text),
+            mpv_log_level::Info => tracing::info!(target: "mpv", prefix, "{}", text),
+            mpv_log_level::V | mpv_log_level::Debug => {
+                tracing::debug!(target: "mpv", prefix, "{}", text)
+            }
+            _ => tracing::trace!(target: "mpv", prefix, "{}", text),
         }
     }
 }
+
+#[cfg(feature = "futures")]
+struct StreamState<'parent> {
+    ctx: EventContext<'parent>,
+    waker: Arc<Mutex<Option<Waker>>>,
+    shutdown: bool,
+}
+
+#[cfg(feature = "futures")]
+impl<'parent> StreamState<'parent> {
+    fn new(mut ctx: EventContext<'parent>) -> StreamState<'parent> {
+        let waker = Arc::new(Mutex::new(None::<Waker>));
+        let wakeup_waker = waker.clone();
+        ctx.set_wakeup_callback(move || {
+            if let Some(waker) = &*wakeup_waker.lock().unwrap() {
+                waker.wake_by_ref();
+            }
+        });
+
+        StreamState {
+            ctx,
+            waker,
+            shutdown: false,
+        }
+    }
+
+    fn poll_next<T, F>(&mut self, cx: &mut task::Context<'_>, mut map: F) -> Poll<Option<T>>
+    where
+        F: for<'a> FnMut(&'parent Mpv, Result<Event<'a>>) -> Option<T>,
+    {
+        // Store the waker before polling, so that no wakeup in between is lost.
+        *self.waker.lock().unwrap() = Some(cx.waker().clone());
+
+        let mpv = self.ctx.mpv;
+        while !self.shutdown {
+            let event = unsafe { *libmpv_sys::mpv_wait_event(self.ctx.ctx.as_ptr(), 0.) };
+            if event.event_id == mpv_event_id::None {
+                return Poll::Pending;
+            }
+            self.shutdown = event.event_id == mpv_event_id::Shutdown;
+
+            if let Some(item) = self
+                .ctx
+                .process_event(event)
+                .and_then(|event| map(mpv, event))
+            {
+                return Poll::Ready(Some(item));
+            }
+        }
+        Poll::Ready(None)
+    }
+}
+
+/// A stream of owned events, created by `EventContext::into_stream`.
+#[cfg(feature = "futures")]
+pub struct EventStream<'parent> {
+    state: StreamState<'parent>,
+}
+
+#[cfg(feature = "futures")]
+impl<'parent> Stream for EventStream<'parent> {
+    type Item = Result<OwnedEvent>;
+
+    fn poll_next(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<Option<Result<OwnedEvent>>> {
+        self.get_mut().state.poll_next(cx, |mpv, event| {
+            Some(event.and_then(|event| event.to_owned_event(mpv)))
+        })
+    }
+}
+
+/// A stream of mapped events, created by `EventContext::into_stream_with`.
+#[cfg(feature = "futures")]
+pub struct MappedEventStream<'parent, F> {
+    state: StreamState<'parent>,
+    map: F,
+}
+
+#[cfg(feature = "futures")]
+impl<'parent, T, F> Stream for MappedEventStream<'parent, F>
+where
+    F: for<'a> FnMut(Result<Event<'a>>) -> Option<T> + Unpin,
+{
+    type Item = T;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<Option<T>> {
+        let this = self.get_mut();
+        let map = &mut this.map;
+        this.state.poll_next(cx, |_, event| map(event))
+    }
+}
@@ -0,0 +1,265 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of libmpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex, PoisonError,
+};
+
+/// The client message mpv sends for `script-binding` commands.
+const KEY_BINDING_MESSAGE: &str = "key-binding";
+
+type KeyHandler = Arc<Mutex<dyn FnMut(&KeyEvent) + Send>>;
+
+/// The key binding handlers of an `Mpv`, which are called by `EventContext::wait_event`.
+pub(crate) struct KeyBindings {
+    next_id: AtomicU64,
+    handlers: Mutex<HashMap<String, KeyHandler>>,
+}
+
+impl KeyBindings {
+    pub(crate) fn new() -> KeyBindings {
+        KeyBindings {
+            next_id: AtomicU64::new(1),
+            handlers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Hand a `key-binding` client message to the handler of the binding it names, if any.
+    pub(crate) fn dispatch(&self, args: &[&str]) {
+        let (name, state, key) = match args {
+            [KEY_BINDING_MESSAGE, name, state, key, ..] => (*name, *state, *key),
+            _ => return,
+        };
+        let is_mouse = state.as_bytes().get(1) == Some(&b'm');
+        let state = match state.as_bytes().first() {
+            Some(b'd') => KeyState::Down,
+            Some(b'u') => KeyState::Up,
+            Some(b'r') => KeyState::Repeat,
+            Some(b'p') => KeyState::Press,
+            _ => return,
+        };
+        let event = KeyEvent {
+            key: key.to_owned(),
+            state,
+            is_mouse,
+        };
+
+        // Don't hold the lock while the handler runs, so that it may change the bindings.
+        let handler = self.handlers.lock().unwrap().get(name).cloned();
+        if let Some(handler) = handler {
+            // A handler that panicked before still receives later events.
+            (handler.lock().unwrap_or_else(PoisonError::into_inner))(&event);
+        }
+    }
+}
+
+/// Whether the bindings of an `InputSection` take precedence over the user's `input.conf`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SectionMode {
+    /// Keys that are bound by the user are not overridden.
+    Default,
+    /// Keys are bound even if the user bound them too.
+    Force,
+}
+
+impl SectionMode {
+    fn as_str(self) -> &'static str {
+        match self {
+            SectionMode::Default => "default",
+            SectionMode::Force => "force",
+        }
+    }
+}
+
+/// What happened to a bound key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KeyState {
+    /// The key was pressed, and is held down.
+    Down,
+    /// The key was released.
+    Up,
+    /// The key is held down, and auto-repeat triggered.
+    Repeat,
+    /// The key was pressed and released at once, e.g. by the `keypress` command or a mouse wheel.
+    Press,
+}
+
+/// A key event of a binding in an `InputSection`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyEvent {
+    /// The name of the key, as normalised by mpv, e.g. `Ctrl+s` for a binding of `ctrl+s`.
+    pub key: String,
+    /// Whether the key went down, up, or repeated.
+    pub state: KeyState,
+    /// Whether the event was caused by the mouse.
+    pub is_mouse: bool,
+}
+
+struct Binding {
+    key: String,
+    name: String,
+}
+
+/// A named section of key bindings, created by `Mpv::input_section`, whose handlers are called by
+/// `EventContext::wait_event` when a bound key is used.
+///
+/// The section has no effect until it is enabled. When it is dropped, it is disabled and its
+/// bindings are removed.
+pub struct InputSection<'parent> {
+    mpv: &'parent Mpv,
+    name: String,
+    mode: SectionMode,
+    bindings: Vec<Binding>,
+}
+
+impl Mpv {
+    /// Define an empty input section `name`, using `define-section`. Defining a section that
+    /// already exists replaces it.
+    pub fn input_section(&self, name: &str, mode: SectionMode) -> Result<InputSection<'_>> {
+        let section = InputSection {
+            mpv: self,
+            name: name.to_owned(),
+            mode,
+            bindings: Vec::new(),
+        };
+        section.define()?;
+        Ok(section)
+    }
+}
+
+impl<'parent> InputSection<'parent> {
+    /// The name of this section.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Bind `key`, e.g. `ctrl+s` or `MBTN_LEFT`, to `handler`, replacing any binding of `key` in
+    /// this section. The handler receives every down, up and repeat event of the key.
+    pub fn bind<F>(&mut self, key: &str, handler: F) -> Result<()>
+    where
+        F: FnMut(&KeyEvent) + Send + 'static,
+    {
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(Error::Raw(mpv_error::InvalidParameter));
+        }
+        let (replaced, kept) = self
+            .bindings
+            .drain(..)
+            .partition(|binding| binding.key == key);
+        self.bindings = kept;
+
+        let bindings = &self.mpv.key_bindings;
+        let name = format!(
+            "libmpv-key-{}",
+            bindings.next_id.fetch_add(1, Ordering::Relaxed)
+        );
+        bindings
+            .handlers
+            .lock()
+            .unwrap()
+            .insert(name.clone(), Arc::new(Mutex::new(handler)));
+        self.bindings.push(Binding {
+            key: key.to_owned(),
+            name,
+        });
+
+        // Only drop the previous binding once mpv uses the new one.
+        let ret = self.define();
+        let stale = if ret.is_ok() {
+            replaced
+        } else {
+            let added = self.bindings.pop().into_iter().collect();
+            self.bindings.extend(replaced);
+            added
+        };
+        let mut handlers = bindings.handlers.lock().unwrap();
+        for binding in stale {
+            handlers.remove(&binding.name);
+        }
+        ret
+    }
+
+    /// Remove the binding of `key` from this section.
+    pub fn unbind(&mut self, key: &str) -> Result<()> {
+        self.remove_binding(key);
+        self.define()
+    }
+
+    /// Enable this section, using `enable-section`. If `exclusive` is set, all sections that were
+    /// enabled before are ignored until this one is disabled.
+    pub fn enable(&self, exclusive: bool) -> Result<()> {
+        if exclusive {
+            self.mpv
+                .command_argv("enable-section", &[&self.name, "exclusive"])
+        } else {
+            self.mpv.command_argv("enable-section", &[&self.name])
+        }
+    }
+
+    /// Disable this section, using `disable-section`.
+    pub fn disable(&self) -> Result<()> {
+        self.mpv.command_argv("disable-section", &[&self.name])
+    }
+
+    fn remove_binding(&mut self, key: &str) {
+        let handlers = &self.mpv.key_bindings.handlers;
+        self.bindings.retain(|binding| {
+            let keep = binding.key != key;
+            if !keep {
+                handlers.lock().unwrap().remove(&binding.name);
+            }
+            keep
+        });
+    }
+
+    /// (Re)define the section with the current bindings, using `define-section`.
+    fn define(&self) -> Result<()> {
+        let client = self.mpv.client_name()?;
+        let contents: String = self
+            .bindings
+            .iter()
+            .map(|binding| {
+                format!(
+                    "{} script-binding {}/{}\n",
+                    binding.key, client, binding.name
+                )
+            })
+            .collect();
+        self.mpv.command_argv(
+            "define-section",
+            &[&self.name, &contents, self.mode.as_str()],
+        )
+    }
+}
+
+impl<'parent> Drop for InputSection<'parent> {
+    fn drop(&mut self) {
+        let _ = self.disable();
+        let _ = self
+            .mpv
+            .command_argv("define-section", &[&self.name, "", self.mode.as_str()]);
+        let mut handlers = self.mpv.key_bindings.handlers.lock().unwrap();
+        for binding in &self.bindings {
+            handlers.remove(&binding.name);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of libmpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+
+use self::reply::ReplySlot;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex, PoisonError,
+};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// Request ids generated by `Mpv::script_request` start with this prefix.
+const REQUEST_ID_PREFIX: &str = "libmpv-request-";
+
+type Handler = Arc<Mutex<dyn FnMut(&[&str]) + Send>>;
+
+/// The message handlers and outstanding script requests of an `Mpv`, which are served by
+/// `EventContext::wait_event`.
+pub(crate) struct MessageBus {
+    next_id: AtomicU64,
+    handlers: Mutex<HashMap<String, Handler>>,
+    requests: Mutex<HashMap<String, Arc<ReplySlot<Vec<String>>>>>,
+}
+
+impl MessageBus {
+    pub(crate) fn new() -> MessageBus {
+        MessageBus {
+            next_id: AtomicU64::new(1),
+            handlers: Mutex::new(HashMap::new()),
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Route a `MPV_EVENT_CLIENT_MESSAGE` to the request or handler named by its first argument.
+    pub(crate) fn dispatch(&self, args: &[&str]) {
+        let (first, rest) = match args.split_first() {
+            Some(split) => split,
+            None => return,
+        };
+
+        if first.starts_with(REQUEST_ID_PREFIX) {
+            let request = self.requests.lock().unwrap().remove(*first);
+            if let Some(slot) = request {
+                slot.fulfill(Ok(rest.iter().map(|&arg| arg.to_owned()).collect()));
+            }
+            return;
+        }
+
+        // Don't hold the lock while the handler runs, so that it may (un)register handlers.
+        let handler = self.handlers.lock().unwrap().get(*first).cloned();
+        if let Some(handler) = handler {
+            // A handler that panicked before still receives later messages.
+            (handler.lock().unwrap_or_else(PoisonError::into_inner))(rest);
+        }
+    }
+}
+
+/// A handler for script messages, registered by `Mpv::register_message_handler`. The handler is
+/// unregistered when this is dropped.
+pub struct MessageHandler<'parent> {
+    mpv: &'parent Mpv,
+    name: String,
+}
+
+impl<'parent> MessageHandler<'parent> {
+    /// The first argument of the messages this handler receives.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<'parent> Drop for MessageHandler<'parent> {
+    fn drop(&mut self) {
+        self.mpv
+            .messages
+            .handlers
+            .lock()
+            .unwrap()
+            .remove(&self.name);
+    }
+}
+
+/// Handle to a request sent by `Mpv::script_request`, which completes with the arguments of the
+/// script's response.
+///
+/// Like `AsyncReply`, this can be polled, waited for, or awaited, and needs some thread to keep
+/// calling `EventContext::wait_event`. Scripts that never respond leave it pending forever, so
+/// prefer `wait_timeout`. Dropping the handle discards the response.
+pub struct ScriptReply<'parent> {
+    mpv: &'parent Mpv,
+    id: String,
+    slot: Arc<ReplySlot<Vec<String>>>,
+}
+
+impl<'parent> ScriptReply<'parent> {
+    /// The generated id that identifies the response.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Return the arguments of the response, if it has arrived yet.
+    pub fn try_result(&self) -> Option<Result<Vec<String>>> {
+        self.slot.try_result()
+    }
+
+    /// Block until the response has arrived, see `AsyncReply::wait`.
+    pub fn wait(self) -> Result<Vec<String>> {
+        self.slot.wait()
+    }
+
+    /// Block until the response has arrived, or `timeout` has passed.
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<Result<Vec<String>>> {
+        self.slot.wait_timeout(timeout)
+    }
+}
+
+impl<'parent> Future for ScriptReply<'parent> {
+    type Output = Result<Vec<String>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Vec<String>>> {
+        self.slot.poll(cx)
+    }
+}
+
+impl<'parent> Drop for ScriptReply<'parent> {
+    fn drop(&mut self) {
+        self.mpv.messages.requests.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl Mpv {
+    /// Call `handler` for every `script-message` and `script-message-to <client-name>` this
+    /// handle receives whose first argument is `name`, with the remaining arguments. Handlers
+    /// run on the thread that calls `EventContext::wait_event`, which still returns the messages
+    /// as `Event::ClientMessage`.
+    ///
+    /// Returns `Error::Raw(mpv_error::InvalidParameter)` if a handler for `name` is already
+    /// registered.
+    pub fn register_message_handler<F>(&self, name: &str, handler: F) -> Result<MessageHandler<'_>>
+    where
+        F: FnMut(&[&str]) + Send + 'static,
+    {
+        let mut handlers = self.messages.handlers.lock().unwrap();
+        if name.starts_with(REQUEST_ID_PREFIX) || handlers.contains_key(name) {
+            return Err(Error::Raw(mpv_error::InvalidParameter));
+        }
+        handlers.insert(name.to_owned(), Arc::new(Mutex::new(handler)));
+
+        Ok(MessageHandler {
+            mpv: self,
+            name: name.to_owned(),
+        })
+    }
+
+    /// Send `args` to all clients and scripts, using `script-message`.
+    pub fn script_message(&self, args: &[&str]) -> Result<()> {
+        self.command_argv("script-message", args)
+    }
+
+    /// Send `args` to the client or script named `target`, using `script-message-to`.
+    pub fn script_message_to(&self, target: &str, args: &[&str]) -> Result<()> {
+        let mut argv = Vec::with_capacity(args.len() + 1);
+        argv.push(target);
+        argv.extend_from_slice(args);
+        self.command_argv("script-message-to", &argv)
+    }
+
+    /// Send the request `name` with `args` to the client or script named `target`, and return a
+    /// handle to its response.
+    ///
+    /// The message sent is `name`, followed by this handle's address and a generated id, and then
+    /// `args`. The script responds by sending a message to the address, whose first argument is
+    /// the id, and whose remaining arguments are the result. In Lua:
+    ///
+    /// ```lua
+    /// mp.register_script_message("ping", function(reply_to, id, ...)
+    ///     mp.commandv("script-message-to", reply_to, id, "pong", ...)
+    /// end)
+    /// ```
+    pub fn script_request(
+        &self,
+        target: &str,
+        name: &str,
+        args: &[&str],
+    ) -> Result<ScriptReply<'_>> {
+        let id = format!(
+            "{}{}",
+            REQUEST_ID_PREFIX,
+            self.messages.next_id.fetch_add(1, Ordering::Relaxed)
+        );
+        let slot = ReplySlot::new();
+        self.messages
+            .requests
+            .lock()
+            .unwrap()
+            .insert(id.clone(), slot.clone());
+        // Construct the handle first, so that the request is forgotten if sending fails.
+        let reply = ScriptReply {
+            mpv: self,
+            id,
+            slot,
+        };
+
+        let reply_to = format!("@{}", self.client_id());
+        let mut argv = Vec::with_capacity(args.len() + 3);
+        argv.extend_from_slice(&[name, &reply_to, &reply.id]);
+        argv.extend_from_slice(args);
+        self.script_message_to(target, &argv)?;
+        Ok(reply)
+    }
+}
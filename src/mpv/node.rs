@@ -0,0 +1,467 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of libmpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+
+use libmpv_sys::{mpv_byte_array, mpv_node, mpv_node__bindgen_ty_1 as mpv_node_u, mpv_node_list};
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::ffi::CString;
+use std::os::raw as ctype;
+use std::ptr;
+use std::slice;
+
+/// An owned mpv node tree, that is independent of mpv's allocator.
+///
+/// Nodes can be built from Rust values with `From`, and converted back with `TryFrom`, e.g.
+/// `Node::from(vec![("sub-auto", "fuzzy")].into_iter().collect::<HashMap<_, _>>())`. They can be
+/// passed to `Mpv::set_property` and `Mpv::command_node`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Node {
+    None,
+    String(String),
+    Flag(bool),
+    Int64(i64),
+    Double(f64),
+    ByteArray(Vec<u8>),
+    Array(Vec<Node>),
+    /// Key value pairs, in the order mpv returned them.
+    Map(Vec<(String, Node)>),
+}
+
+impl Node {
+    /// Deep-copy a node tree.
+    ///
+    /// # Safety
+    /// `node` has to be a valid node, as returned by mpv.
+    pub(crate) unsafe fn from_raw(node: &mpv_node) -> Result<Node> {
+        Ok(match node.format {
+            mpv_format::None => Node::None,
+            mpv_format::String => Node::String(mpv_cstr_to_str!(node.u.string)?.to_owned()),
+            mpv_format::Flag => Node::Flag(node.u.flag != 0),
+            mpv_format::Int64 => Node::Int64(node.u.int64),
+            mpv_format::Double => Node::Double(node.u.double_),
+            mpv_format::ByteArray => {
+                let ba = &*node.u.ba;
+                if ba.size == 0 {
+                    Node::ByteArray(Vec::new())
+                } else {
+                    Node::ByteArray(
+                        slice::from_raw_parts(ba.data as *const u8, ba.size as _).to_vec(),
+                    )
+                }
+            }
+            mpv_format::Array => Node::Array(
+                node_list_values(node.u.list)
+                    .iter()
+                    .map(|value| Node::from_raw(value))
+                    .collect::<Result<_>>()?,
+            ),
+            mpv_format::Map => {
+                let values = node_list_values(node.u.list);
+                let keys = (*node.u.list).keys;
+                let mut map = Vec::with_capacity(values.len());
+                for (i, value) in values.iter().enumerate() {
+                    let key = mpv_cstr_to_str!(*keys.add(i))?.to_owned();
+                    map.push((key, Node::from_raw(value)?));
+                }
+                Node::Map(map)
+            }
+            _ => return Err(Error::Raw(mpv_error::PropertyFormat)),
+        })
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        if let Node::Flag(value) = *self {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        if let Node::Int64(value) = *self {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// Also converts `Int64`s, since mpv does not distinguish them when setting values.
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Node::Double(value) => Some(value),
+            Node::Int64(value) => Some(value as f64),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        if let Node::String(value) = self {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        if let Node::ByteArray(value) = self {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Node]> {
+        if let Node::Array(value) = self {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_map(&self) -> Option<&[(String, Node)]> {
+        if let Node::Map(value) = self {
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// Look up the value of `key`, if this is a `Map`.
+    pub fn get(&self, key: &str) -> Option<&Node> {
+        self.as_map()?
+            .iter()
+            .find_map(|(k, value)| if k == key { Some(value) } else { None })
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl Node {
+    /// Render the node as JSON, the same way mpv's JSON IPC does. Byte arrays become arrays of
+    /// numbers, and doubles that are not finite become `null`.
+    pub fn to_json(&self) -> serde_json::Value {
+        use serde_json::Value;
+
+        match self {
+            Node::None => Value::Null,
+            Node::String(value) => Value::String(value.clone()),
+            Node::Flag(value) => Value::Bool(*value),
+            Node::Int64(value) => Value::from(*value),
+            Node::Double(value) => serde_json::Number::from_f64(*value)
+                .map(Value::Number)
+                .unwrap_or(Value::Null),
+            Node::ByteArray(value) => Value::from(value.as_slice()),
+            Node::Array(values) => Value::Array(values.iter().map(Node::to_json).collect()),
+            Node::Map(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.to_json()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "serde_json")]
+impl From<Node> for serde_json::Value {
+    fn from(node: Node) -> serde_json::Value {
+        node.to_json()
+    }
+}
+
+unsafe impl GetData for Node {
+    fn get_from_c_void<T, F: FnMut(*mut ctype::c_void) -> Result<T>>(fun: F) -> Result<Node> {
+        MpvNode::get_from_c_void(fun)?.to_node()
+    }
+
+    fn get_format() -> Format {
+        Format::Node
+    }
+}
+
+unsafe impl SetData for Node {
+    fn call_as_c_void<T, F: FnMut(*mut ctype::c_void) -> Result<T>>(self, mut fun: F) -> Result<T> {
+        let mut arena = NodeArena::default();
+        let mut node = arena.node(&self)?;
+        fun(&mut node as *mut mpv_node as *mut _)
+    }
+
+    fn get_format() -> Format {
+        Format::Node
+    }
+}
+
+impl From<bool> for Node {
+    fn from(value: bool) -> Node {
+        Node::Flag(value)
+    }
+}
+
+impl From<i64> for Node {
+    fn from(value: i64) -> Node {
+        Node::Int64(value)
+    }
+}
+
+impl From<f64> for Node {
+    fn from(value: f64) -> Node {
+        Node::Double(value)
+    }
+}
+
+impl From<String> for Node {
+    fn from(value: String) -> Node {
+        Node::String(value)
+    }
+}
+
+impl<'a> From<&'a str> for Node {
+    fn from(value: &'a str) -> Node {
+        Node::String(value.to_owned())
+    }
+}
+
+impl<T: Into<Node>> From<Vec<T>> for Node {
+    fn from(values: Vec<T>) -> Node {
+        Node::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+/// The entries of the resulting `Map` are in arbitrary order.
+impl<K: Into<String>, V: Into<Node>> From<HashMap<K, V>> for Node {
+    fn from(map: HashMap<K, V>) -> Node {
+        Node::Map(
+            map.into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        )
+    }
+}
+
+impl TryFrom<Node> for bool {
+    type Error = Error;
+
+    fn try_from(node: Node) -> Result<bool> {
+        node.as_bool().ok_or(Error::Raw(mpv_error::PropertyFormat))
+    }
+}
+
+impl TryFrom<Node> for i64 {
+    type Error = Error;
+
+    fn try_from(node: Node) -> Result<i64> {
+        node.as_i64().ok_or(Error::Raw(mpv_error::PropertyFormat))
+    }
+}
+
+impl TryFrom<Node> for f64 {
+    type Error = Error;
+
+    fn try_from(node: Node) -> Result<f64> {
+        node.as_f64().ok_or(Error::Raw(mpv_error::PropertyFormat))
+    }
+}
+
+impl TryFrom<Node> for String {
+    type Error = Error;
+
+    fn try_from(node: Node) -> Result<String> {
+        match node {
+            Node::String(value) => Ok(value),
+            _ => Err(Error::Raw(mpv_error::PropertyFormat)),
+        }
+    }
+}
+
+impl<T: TryFrom<Node, Error = Error>> TryFrom<Node> for Vec<T> {
+    type Error = Error;
+
+    fn try_from(node: Node) -> Result<Vec<T>> {
+        match node {
+            Node::Array(values) => values.into_iter().map(T::try_from).collect(),
+            _ => Err(Error::Raw(mpv_error::PropertyFormat)),
+        }
+    }
+}
+
+impl<T: TryFrom<Node, Error = Error>> TryFrom<Node> for HashMap<String, T> {
+    type Error = Error;
+
+    fn try_from(node: Node) -> Result<HashMap<String, T>> {
+        match node {
+            Node::Map(entries) => entries
+                .into_iter()
+                .map(|(key, value)| Ok((key, T::try_from(value)?)))
+                .collect(),
+            _ => Err(Error::Raw(mpv_error::PropertyFormat)),
+        }
+    }
+}
+
+impl MpvNode {
+    /// Deep-copy this node into an owned `Node`.
+    pub fn to_node(&self) -> Result<Node> {
+        self.as_node_ref().to_node()
+    }
+}
+
+impl<'a> MpvNodeRef<'a> {
+    /// Deep-copy this node and its children into an owned `Node`.
+    pub fn to_node(self) -> Result<Node> {
+        unsafe { Node::from_raw(self.0) }
+    }
+}
+
+/// Owns the memory of an `mpv_node` tree that was built on the Rust side. Nodes handed out by
+/// the arena are valid as long as the arena is alive, and must not be passed to
+/// `mpv_free_node_contents`.
+#[derive(Default)]
+pub(crate) struct NodeArena {
+    strings: Vec<CString>,
+    values: Vec<Vec<mpv_node>>,
+    keys: Vec<Vec<*mut ctype::c_char>>,
+    bytes: Vec<Vec<u8>>,
+    // Boxed for the same reason as `lists`.
+    #[allow(clippy::vec_box)]
+    byte_arrays: Vec<Box<mpv_byte_array>>,
+    // Boxed, so that the lists don't move when the `Vec` grows.
+    #[allow(clippy::vec_box)]
+    lists: Vec<Box<mpv_node_list>>,
+}
+
+impl NodeArena {
+    /// Build the raw representation of `node`.
+    pub(crate) fn node(&mut self, node: &Node) -> Result<mpv_node> {
+        Ok(match node {
+            Node::None => mpv_node {
+                u: mpv_node_u { int64: 0 },
+                format: mpv_format::None,
+            },
+            Node::String(value) => self.string(value)?,
+            Node::Flag(value) => NodeArena::flag(*value),
+            Node::Int64(value) => mpv_node {
+                u: mpv_node_u { int64: *value },
+                format: mpv_format::Int64,
+            },
+            Node::Double(value) => mpv_node {
+                u: mpv_node_u { double_: *value },
+                format: mpv_format::Double,
+            },
+            Node::ByteArray(value) => {
+                let mut bytes = value.clone();
+                let mut ba = Box::new(mpv_byte_array {
+                    data: bytes.as_mut_ptr() as *mut _,
+                    size: bytes.len() as _,
+                });
+                let node = mpv_node {
+                    u: mpv_node_u {
+                        ba: &mut *ba as *mut _,
+                    },
+                    format: mpv_format::ByteArray,
+                };
+                self.bytes.push(bytes);
+                self.byte_arrays.push(ba);
+                node
+            }
+            Node::Array(values) => {
+                let values = values
+                    .iter()
+                    .map(|value| self.node(value))
+                    .collect::<Result<_>>()?;
+                self.array(values)
+            }
+            Node::Map(entries) => {
+                let values = entries
+                    .iter()
+                    .map(|(key, value)| Ok((key.as_str(), self.node(value)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                self.map(values)?
+            }
+        })
+    }
+
+    pub(crate) fn flag(value: bool) -> mpv_node {
+        mpv_node {
+            u: mpv_node_u {
+                flag: value as ctype::c_int,
+            },
+            format: mpv_format::Flag,
+        }
+    }
+
+    pub(crate) fn string(&mut self, value: &str) -> Result<mpv_node> {
+        let value = CString::new(value)?;
+        let node = mpv_node {
+            u: mpv_node_u {
+                string: value.as_ptr() as *mut _,
+            },
+            format: mpv_format::String,
+        };
+        self.strings.push(value);
+        Ok(node)
+    }
+
+    pub(crate) fn array(&mut self, mut values: Vec<mpv_node>) -> mpv_node {
+        let mut list = Box::new(mpv_node_list {
+            num: values.len() as _,
+            values: values.as_mut_ptr(),
+            keys: ptr::null_mut(),
+        });
+        let node = mpv_node {
+            u: mpv_node_u {
+                list: &mut *list as *mut _,
+            },
+            format: mpv_format::Array,
+        };
+        self.values.push(values);
+        self.lists.push(list);
+        node
+    }
+
+    pub(crate) fn map<'k, I>(&mut self, entries: I) -> Result<mpv_node>
+    where
+        I: IntoIterator<Item = (&'k str, mpv_node)>,
+    {
+        let mut keys = Vec::new();
+        let mut values = Vec::new();
+        for (key, value) in entries {
+            let key = CString::new(key)?;
+            keys.push(key.as_ptr() as *mut _);
+            values.push(value);
+            self.strings.push(key);
+        }
+
+        let mut list = Box::new(mpv_node_list {
+            num: values.len() as _,
+            values: values.as_mut_ptr(),
+            keys: keys.as_mut_ptr(),
+        });
+        let node = mpv_node {
+            u: mpv_node_u {
+                list: &mut *list as *mut _,
+            },
+            format: mpv_format::Map,
+        };
+        self.values.push(values);
+        self.keys.push(keys);
+        self.lists.push(list);
+        Ok(node)
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of libmpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+/// An ASS overlay on the OSD, created by `Mpv::create_osd_overlay`. The overlay is removed when
+/// this is dropped.
+///
+/// The data is a list of ASS events separated by newlines, where only the text part of each event
+/// is given, e.g. `{\an7\fs20}Buffering...`. Positions are relative to the resolution of the
+/// overlay, which is scaled to the window.
+pub struct OsdOverlay<'parent> {
+    mpv: &'parent Mpv,
+    id: i64,
+    data: String,
+    res_x: i64,
+    res_y: i64,
+    z: i64,
+    removed: bool,
+}
+
+impl Mpv {
+    /// Show `text` on the OSD, using `show-text`. `duration` defaults to the `osd-duration` option,
+    /// and the text is only shown if the `osd-level` option is at least `level`, which defaults to
+    /// 1.
+    ///
+    /// Property expansion such as `${time-pos}` is applied to `text`.
+    pub fn show_text(
+        &self,
+        text: &str,
+        duration: Option<Duration>,
+        level: Option<u8>,
+    ) -> Result<()> {
+        let duration = duration.map_or(-1, |duration| duration.as_millis() as i64);
+        match level {
+            Some(level) => self.command_argv(
+                "show-text",
+                &[text, &duration.to_string(), &level.to_string()],
+            ),
+            None => self.command_argv("show-text", &[text, &duration.to_string()]),
+        }
+    }
+
+    /// Show the progress bar, the elapsed time and the total duration on the OSD, using
+    /// `show-progress`. Like with `show_text`, nothing is shown unless the `osd-level` option is
+    /// at least `level`, which defaults to 1.
+    ///
+    /// `show-progress` takes no arguments and always uses the `osd-duration` option, so
+    /// `duration` is applied by setting that option while the command runs, and restoring it
+    /// afterwards. `level` is compared to `osd-level` before the command runs, so a change of
+    /// `osd-level` in between, e.g. by another client, is not taken into account.
+    pub fn show_progress(&self, duration: Option<Duration>, level: Option<u8>) -> Result<()> {
+        if self.get_property::<i64>("osd-level")? < i64::from(level.unwrap_or(1)) {
+            return Ok(());
+        }
+        let duration = match duration {
+            Some(duration) => duration,
+            None => return self.command_argv("show-progress", &[]),
+        };
+
+        // mpv reads the option when the command runs, so it can be restored right away.
+        let previous = self.get_property::<i64>("osd-duration")?;
+        self.set_property("osd-duration", duration.as_millis() as i64)?;
+        let ret = self.command_argv("show-progress", &[]);
+        let restored = self.set_property("osd-duration", previous);
+        ret.and(restored)
+    }
+
+    /// Create an overlay with the ASS events `data` on the OSD, using `osd-overlay`. `res_x` and
+    /// `res_y` are the coordinate space of `data`, where `0` for `res_x` means that it is
+    /// computed from `res_y` and the aspect ratio of the window. Overlays with a higher `z` are
+    /// drawn over those with a lower one.
+    pub fn create_osd_overlay(
+        &self,
+        data: &str,
+        res_x: i64,
+        res_y: i64,
+        z: i64,
+    ) -> Result<OsdOverlay<'_>> {
+        let overlay = OsdOverlay {
+            mpv: self,
+            id: self.osd_overlay_ids.fetch_add(1, Ordering::Relaxed) as i64,
+            data: data.to_owned(),
+            res_x,
+            res_y,
+            z,
+            removed: false,
+        };
+        overlay.send()?;
+        Ok(overlay)
+    }
+}
+
+impl<'parent> OsdOverlay<'parent> {
+    /// The id of this overlay, which is unique per client handle.
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// Replace the ASS events of this overlay.
+    pub fn update(&mut self, data: &str) -> Result<()> {
+        self.data = data.to_owned();
+        self.send()
+    }
+
+    /// Change the coordinate space of this overlay.
+    pub fn set_resolution(&mut self, res_x: i64, res_y: i64) -> Result<()> {
+        self.res_x = res_x;
+        self.res_y = res_y;
+        self.send()
+    }
+
+    /// Change the z-order of this overlay.
+    pub fn set_z(&mut self, z: i64) -> Result<()> {
+        self.z = z;
+        self.send()
+    }
+
+    /// Remove this overlay, reporting any error, unlike dropping it.
+    pub fn remove(mut self) -> Result<()> {
+        self.command("none", "")?;
+        self.removed = true;
+        Ok(())
+    }
+
+    fn send(&self) -> Result<()> {
+        self.command("ass-events", &self.data)
+    }
+
+    fn command(&self, format: &str, data: &str) -> Result<()> {
+        self.mpv
+            .command_node(Node::Map(vec![
+                ("name".to_owned(), "osd-overlay".into()),
+                ("id".to_owned(), self.id.into()),
+                ("format".to_owned(), format.into()),
+                ("data".to_owned(), data.into()),
+                ("res_x".to_owned(), self.res_x.into()),
+                ("res_y".to_owned(), self.res_y.into()),
+                ("z".to_owned(), self.z.into()),
+            ]))
+            .map(|_| ())
+    }
+}
+
+impl<'parent> Drop for OsdOverlay<'parent> {
+    fn drop(&mut self) {
+        if !self.removed {
+            let _ = self.command("none", "");
+        }
+    }
+}
@@ -0,0 +1,214 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of libmpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+
+use std::mem;
+use std::sync::Mutex;
+
+/// mpv supports the overlay ids `0..64`, which are shared by all clients of a core, unlike the
+/// ids of `osd-overlay`.
+const MAX_OVERLAYS: u8 = 64;
+
+/// The overlay ids that are in use, as bit set. It is shared by an `Mpv` and all clients created
+/// from it.
+pub(crate) struct OverlayIds(Mutex<u64>);
+
+impl OverlayIds {
+    pub(crate) fn new() -> OverlayIds {
+        OverlayIds(Mutex::new(0))
+    }
+
+    fn acquire(&self) -> Option<u8> {
+        let mut used = self.0.lock().unwrap();
+        let id = (!*used).trailing_zeros() as u8;
+        if id >= MAX_OVERLAYS {
+            return None;
+        }
+        *used |= 1 << id;
+        Some(id)
+    }
+
+    fn release(&self, id: u8) {
+        *self.0.lock().unwrap() &= !(1 << id);
+    }
+}
+
+/// A bitmap overlay, created by `Mpv::create_overlay`, that shows a BGRA image over the video.
+/// The overlay is removed when this is dropped.
+///
+/// The pixels are premultiplied BGRA, 4 bytes per pixel, without padding between rows. They are
+/// kept in buffers owned by this overlay, whose address is passed to mpv with `overlay-add`. An
+/// update writes to the buffer mpv does not currently reference, and then points mpv to it, so
+/// mpv never reads pixels while they are being written. Passing the pixels as a file or file
+/// descriptor, which `overlay-add` also supports, is not implemented.
+///
+/// If mpv fails to remove the overlay when it is dropped, the buffers are leaked and its id stays
+/// in use, since mpv may still read from them.
+///
+/// Ids are only coordinated between the handles of this crate, so other clients of the same core,
+/// e.g. scripts, must not use `overlay-add` themselves.
+pub struct Overlay<'parent> {
+    mpv: &'parent Mpv,
+    id: u8,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    buffers: [Box<[u8]>; 2],
+    current: usize,
+    removed: bool,
+}
+
+impl Mpv {
+    /// Create an overlay of `width` by `height` transparent pixels, whose top left corner is
+    /// shown at `x`, `y` in window coordinates.
+    ///
+    /// Returns `Error::Raw(mpv_error::InvalidParameter)` if the size is zero, or if this handle
+    /// and its parent and clients already have 64 overlays.
+    pub fn create_overlay(&self, x: i32, y: i32, width: u32, height: u32) -> Result<Overlay<'_>> {
+        if width == 0 || height == 0 {
+            return Err(Error::Raw(mpv_error::InvalidParameter));
+        }
+        let id = self
+            .overlay_ids
+            .acquire()
+            .ok_or(Error::Raw(mpv_error::InvalidParameter))?;
+        let len = width as usize * height as usize * 4;
+
+        let overlay = Overlay {
+            mpv: self,
+            id,
+            x,
+            y,
+            width,
+            height,
+            buffers: [
+                vec![0; len].into_boxed_slice(),
+                vec![0; len].into_boxed_slice(),
+            ],
+            current: 0,
+            removed: false,
+        };
+        overlay.add()?;
+        Ok(overlay)
+    }
+}
+
+impl<'parent> Overlay<'parent> {
+    /// The id passed to `overlay-add`, which is unique among the overlays of an `Mpv` and its
+    /// clients.
+    pub fn id(&self) -> u8 {
+        self.id
+    }
+
+    /// The width and height of this overlay in pixels.
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// The position of the top left corner of this overlay.
+    pub fn position(&self) -> (i32, i32) {
+        (self.x, self.y)
+    }
+
+    /// The pixels currently shown.
+    pub fn pixels(&self) -> &[u8] {
+        &self.buffers[self.current]
+    }
+
+    /// Replace the pixels of this overlay. Returns `Error::Raw(mpv_error::InvalidParameter)` if
+    /// `pixels` is not exactly `width * height * 4` bytes long.
+    pub fn update(&mut self, pixels: &[u8]) -> Result<()> {
+        if pixels.len() != self.buffers[0].len() {
+            return Err(Error::Raw(mpv_error::InvalidParameter));
+        }
+        self.update_with(|buffer| buffer.copy_from_slice(pixels))
+    }
+
+    /// Draw the pixels of this overlay with `draw`, which receives a copy of the pixels currently
+    /// shown.
+    pub fn update_with<F: FnOnce(&mut [u8])>(&mut self, draw: F) -> Result<()> {
+        let (first, second) = self.buffers.split_at_mut(1);
+        let (current, next) = if self.current == 0 {
+            (&first[0], &mut second[0])
+        } else {
+            (&second[0], &mut first[0])
+        };
+        next.copy_from_slice(current);
+        draw(next);
+
+        self.current = 1 - self.current;
+        let ret = self.add();
+        if ret.is_err() {
+            // mpv still references the previous buffer.
+            self.current = 1 - self.current;
+        }
+        ret
+    }
+
+    /// Move the top left corner of this overlay to `x`, `y`.
+    pub fn set_position(&mut self, x: i32, y: i32) -> Result<()> {
+        self.x = x;
+        self.y = y;
+        self.add()
+    }
+
+    /// Remove this overlay, reporting any error, unlike dropping it.
+    pub fn remove(mut self) -> Result<()> {
+        self.mpv
+            .command_argv("overlay-remove", &[&self.id.to_string()])?;
+        self.removed = true;
+        Ok(())
+    }
+
+    /// Show the current buffer, using `overlay-add`.
+    fn add(&self) -> Result<()> {
+        let buffer = &self.buffers[self.current];
+        self.mpv.command_argv(
+            "overlay-add",
+            &[
+                &self.id.to_string(),
+                &self.x.to_string(),
+                &self.y.to_string(),
+                &format!("&{}", buffer.as_ptr() as usize),
+                "0",
+                "bgra",
+                &self.width.to_string(),
+                &self.height.to_string(),
+                &(self.width as usize * 4).to_string(),
+            ],
+        )
+    }
+}
+
+impl<'parent> Drop for Overlay<'parent> {
+    fn drop(&mut self) {
+        // The buffers are only freed after mpv stopped referencing them.
+        if !self.removed
+            && self
+                .mpv
+                .command_argv("overlay-remove", &[&self.id.to_string()])
+                .is_err()
+        {
+            mem::forget(mem::take(&mut self.buffers));
+            return;
+        }
+        self.mpv.overlay_ids.release(self.id);
+    }
+}
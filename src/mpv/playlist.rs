@@ -0,0 +1,208 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of libmpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+
+use std::convert::{TryFrom, TryInto};
+
+/// An entry of the playlist.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaylistEntry {
+    /// The unique ID of this entry, which does not change when the playlist is reordered.
+    pub id: i64,
+    pub filename: String,
+    /// The title, if the file or playlist file provided one.
+    pub title: Option<String>,
+    /// Whether this is the entry the player is on, or about to switch to.
+    pub current: bool,
+    /// Whether this entry is being played.
+    pub playing: bool,
+}
+
+/// A snapshot of the `playlist` property.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Playlist {
+    pub entries: Vec<PlaylistEntry>,
+}
+
+/// A change between two playlist snapshots, as computed by `Diff::diff`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlaylistChange {
+    /// `entry` was inserted at `index`.
+    Inserted { index: usize, entry: PlaylistEntry },
+    /// The entry `id` at `index` was removed.
+    Removed { index: usize, id: i64 },
+    /// The entry `id` was moved from index `from` to index `to`.
+    Moved { id: i64, from: usize, to: usize },
+    /// The title or the flags of the entry at `index` changed.
+    Updated { index: usize, entry: PlaylistEntry },
+}
+
+impl Playlist {
+    /// The entry the player is on, if any.
+    pub fn current(&self) -> Option<&PlaylistEntry> {
+        self.entries.iter().find(|entry| entry.current)
+    }
+
+    /// The entry that is being played, if any.
+    pub fn playing(&self) -> Option<&PlaylistEntry> {
+        self.entries.iter().find(|entry| entry.playing)
+    }
+
+    /// The index of the entry `id`.
+    pub fn position(&self, id: i64) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.id == id)
+    }
+
+    /// The entry `id`.
+    pub fn get(&self, id: i64) -> Option<&PlaylistEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+}
+
+impl Diff for Playlist {
+    type Change = PlaylistChange;
+
+    /// Compute changes that turn `self` into `new`, when applied in order. Entries are matched by
+    /// their ID, so a file that is added twice is two entries.
+    fn diff(&self, new: &Playlist) -> Vec<PlaylistChange> {
+        let mut changes = Vec::new();
+
+        // Remove from the back, so that the indices stay valid while applying the changes.
+        let mut ids: Vec<i64> = self.entries.iter().map(|entry| entry.id).collect();
+        for index in (0..ids.len()).rev() {
+            if new.get(ids[index]).is_none() {
+                changes.push(PlaylistChange::Removed {
+                    index,
+                    id: ids.remove(index),
+                });
+            }
+        }
+
+        // Afterwards, `ids[..index]` always matches the new playlist.
+        for (index, entry) in new.entries.iter().enumerate() {
+            match ids[index..].iter().position(|&id| id == entry.id) {
+                Some(offset) => {
+                    if offset != 0 {
+                        ids.remove(index + offset);
+                        ids.insert(index, entry.id);
+                        changes.push(PlaylistChange::Moved {
+                            id: entry.id,
+                            from: index + offset,
+                            to: index,
+                        });
+                    }
+                    if self.get(entry.id) != Some(entry) {
+                        changes.push(PlaylistChange::Updated {
+                            index,
+                            entry: entry.clone(),
+                        });
+                    }
+                }
+                None => {
+                    ids.insert(index, entry.id);
+                    changes.push(PlaylistChange::Inserted {
+                        index,
+                        entry: entry.clone(),
+                    });
+                }
+            }
+        }
+
+        changes
+    }
+}
+
+impl TryFrom<Node> for PlaylistEntry {
+    type Error = Error;
+
+    fn try_from(node: Node) -> Result<PlaylistEntry> {
+        let format_err = || Error::Raw(mpv_error::PropertyFormat);
+        let flag = |key| node.get(key).and_then(Node::as_bool).unwrap_or(false);
+        Ok(PlaylistEntry {
+            id: node
+                .get("id")
+                .and_then(Node::as_i64)
+                .ok_or_else(format_err)?,
+            filename: node
+                .get("filename")
+                .and_then(Node::as_str)
+                .ok_or_else(format_err)?
+                .to_owned(),
+            title: node.get("title").and_then(Node::as_str).map(str::to_owned),
+            current: flag("current"),
+            playing: flag("playing"),
+        })
+    }
+}
+
+impl TryFrom<Node> for Playlist {
+    type Error = Error;
+
+    fn try_from(node: Node) -> Result<Playlist> {
+        Ok(Playlist {
+            entries: node.try_into()?,
+        })
+    }
+}
+
+/// Watches the playlist, created by `Mpv::watch_playlist`, and reports `PlaylistChange`s.
+pub type PlaylistWatcher<'parent> = DiffWatcher<'parent, Playlist>;
+
+impl Mpv {
+    /// Get a snapshot of the playlist.
+    pub fn playlist(&self) -> Result<Playlist> {
+        self.get_node_property("playlist")
+    }
+
+    /// Watch the playlist. The first update reports the entries that already exist as inserted.
+    pub fn watch_playlist(&self) -> Result<PlaylistWatcher<'_>> {
+        self.watch_diff("playlist")
+    }
+
+    /// Move the entry `id` before the entry `before`, or to the end if `before` is `None`.
+    ///
+    /// Returns `Error::Raw(mpv_error::InvalidParameter)` if one of the entries does not exist.
+    ///
+    /// This is not atomic: mpv's commands only take indices, which are looked up in a snapshot of
+    /// the playlist first. If another client, or mpv itself, changes the playlist in between, a
+    /// different entry may be moved.
+    pub fn playlist_move_id(&self, id: i64, before: Option<i64>) -> Result<()> {
+        let playlist = self.playlist()?;
+        let not_found = || Error::Raw(mpv_error::InvalidParameter);
+        let from = playlist.position(id).ok_or_else(not_found)?;
+        let to = match before {
+            Some(before) => playlist.position(before).ok_or_else(not_found)?,
+            None => playlist.entries.len(),
+        };
+        self.command_argv("playlist-move", &[&from.to_string(), &to.to_string()])
+    }
+
+    /// Remove the entry `id` from the playlist.
+    ///
+    /// Returns `Error::Raw(mpv_error::InvalidParameter)` if the entry does not exist. Like
+    /// `playlist_move_id`, this is not atomic, and may remove a different entry if the playlist
+    /// changes concurrently.
+    pub fn playlist_remove_id(&self, id: i64) -> Result<()> {
+        let index = self
+            .playlist()?
+            .position(id)
+            .ok_or(Error::Raw(mpv_error::InvalidParameter))?;
+        self.playlist_remove_index(index)
+    }
+}
@@ -0,0 +1,305 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of libmpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+//! A catalogue of commonly used mpv properties, with the type they are read and written as.
+//!
+//! ```ignore
+//! mpv.set(props::Pause, true)?;
+//! let pos: Option<f64> = mpv.get(props::TimePos)?;
+//! let lang: Option<String> = mpv.get(props::TrackListLang(0))?;
+//! ```
+//!
+//! Properties that are read-only only implement `Property`, all others also implement
+//! `WritableProperty`. See the [mpv manual](https://mpv.io/manual/master/#properties) for the
+//! meaning of each property.
+//!
+//! This is a subset of the properties mpv documents. Others can be accessed with
+//! `Mpv::get_property` and `Mpv::set_property`, or typed by implementing `Property`. Properties
+//! whose name would clash with a type in `std` or this crate, such as `std::time::Duration` or
+//! `Playlist`, have a `Prop` suffix, so that the module can be glob-imported.
+
+use super::*;
+
+use std::borrow::Cow;
+
+/// A property with a known name and value type.
+pub trait Property {
+    /// The type the property is read as, which also determines the `Format` used.
+    type Value: GetData;
+
+    /// The name of the property, as passed to `Mpv::get_property`.
+    fn name(&self) -> Cow<'static, str>;
+}
+
+/// A property that can be set.
+pub trait WritableProperty: Property
+where
+    Self::Value: SetData,
+{
+}
+
+impl Mpv {
+    /// Get the value of a typed property. Returns `None` if the property is currently
+    /// unavailable, e.g. `TimePos` while nothing is playing.
+    pub fn get<P: Property>(&self, property: P) -> Result<Option<P::Value>> {
+        match self.get_property(&property.name()) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) if e.code() == Some(mpv_error::PropertyUnavailable) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Set the value of a typed property.
+    pub fn set<P>(&self, property: P, value: P::Value) -> Result<()>
+    where
+        P: WritableProperty,
+        P::Value: SetData,
+    {
+        self.set_property(&property.name(), value)
+    }
+}
+
+macro_rules! properties {
+    ($( $(#[$attr:meta])* $ty:ident: $value:ty = $name:literal, $access:ident; )*) => {
+        $(
+            $(#[$attr])*
+            #[doc = concat!("\n\n`", $name, "`")]
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+            pub struct $ty;
+
+            impl Property for $ty {
+                type Value = $value;
+
+                fn name(&self) -> Cow<'static, str> {
+                    Cow::Borrowed($name)
+                }
+            }
+
+            properties!(@access $ty, $access);
+        )*
+    };
+    (@access $ty:ident, rw) => {
+        impl WritableProperty for $ty {}
+    };
+    (@access $ty:ident, ro) => {};
+}
+
+macro_rules! indexed_properties {
+    ($( $(#[$attr:meta])* $ty:ident: $value:ty = $list:literal / $field:literal, $access:ident; )*) => {
+        $(
+            $(#[$attr])*
+            #[doc = concat!("\n\n`", $list, "/N/", $field, "`, where `N` is the wrapped index.")]
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+            pub struct $ty(pub usize);
+
+            impl Property for $ty {
+                type Value = $value;
+
+                fn name(&self) -> Cow<'static, str> {
+                    Cow::Owned(format!(concat!($list, "/{}/", $field), self.0))
+                }
+            }
+
+            properties!(@access $ty, $access);
+        )*
+    };
+}
+
+properties! {
+    // --- Playback ---
+    /// Whether playback is paused.
+    Pause: bool = "pause", rw;
+    /// Playback speed factor.
+    Speed: f64 = "speed", rw;
+    /// Position in the current file in seconds. Setting it seeks.
+    TimePos: f64 = "time-pos", rw;
+    /// Position in the current file in seconds, clamped to the file duration.
+    PlaybackTime: f64 = "playback-time", rw;
+    /// Remaining length of the file in seconds.
+    TimeRemaining: f64 = "time-remaining", ro;
+    /// Position in the current file in percent. Setting it seeks.
+    PercentPos: f64 = "percent-pos", rw;
+    /// Duration of the current file in seconds.
+    DurationProp: f64 = "duration", ro;
+    /// Whether the player is idle, i.e. nothing is loaded.
+    IdleActive: bool = "idle-active", ro;
+    /// Whether playback is stopped or paused, for whatever reason.
+    CoreIdle: bool = "core-idle", ro;
+    /// Whether the end of the file was reached while `keep-open` is enabled.
+    EofReached: bool = "eof-reached", ro;
+    /// Whether the player is currently seeking.
+    Seeking: bool = "seeking", ro;
+    /// Whether playback is paused because the cache is filling.
+    PausedForCache: bool = "paused-for-cache", ro;
+    /// How far the cache is filled before playback resumes, in percent.
+    CacheBufferingState: i64 = "cache-buffering-state", ro;
+    /// How to loop the current file, e.g. `"inf"`, `"no"` or a number.
+    LoopFile: String = "loop-file", rw;
+    /// How to loop the playlist, e.g. `"inf"`, `"no"` or a number.
+    LoopPlaylist: String = "loop-playlist", rw;
+
+    // --- Current file ---
+    /// File name of the current file, without directories.
+    Filename: String = "filename", ro;
+    /// Full path of the current file, as it was given to mpv.
+    PathProp: String = "path", ro;
+    /// The title of the current file, or its file name if there is none.
+    MediaTitle: String = "media-title", ro;
+    /// Size of the current file in bytes.
+    FileSize: i64 = "file-size", ro;
+    /// Name of the container format.
+    FileFormat: String = "file-format", ro;
+    /// Metadata of the current file, as map.
+    Metadata: Node = "metadata", ro;
+    /// State of the demuxer cache, including the seekable ranges.
+    DemuxerCacheState: Node = "demuxer-cache-state", ro;
+    /// Duration of the demuxer cache in seconds.
+    DemuxerCacheDuration: f64 = "demuxer-cache-duration", ro;
+
+    // --- Audio ---
+    /// Volume in percent, where 100 means no amplification.
+    Volume: f64 = "volume", rw;
+    /// Maximum volume in percent.
+    VolumeMax: f64 = "volume-max", rw;
+    /// Whether audio is muted.
+    Mute: bool = "mute", rw;
+    /// Audio delay in seconds.
+    AudioDelay: f64 = "audio-delay", rw;
+    /// The selected audio track, e.g. `"1"`, `"auto"` or `"no"`.
+    Aid: String = "aid", rw;
+    /// Name of the audio codec.
+    AudioCodecName: String = "audio-codec-name", ro;
+    /// Format, sample rate and channel layout of the decoded audio.
+    AudioParams: Node = "audio-params", ro;
+
+    // --- Video ---
+    /// The selected video track, e.g. `"1"`, `"auto"` or `"no"`.
+    Vid: String = "vid", rw;
+    /// Name of the video codec.
+    VideoCodec: String = "video-codec", ro;
+    /// Format, size and colorimetry of the decoded video.
+    VideoParams: Node = "video-params", ro;
+    /// Width of the decoded video.
+    Width: i64 = "width", ro;
+    /// Height of the decoded video.
+    Height: i64 = "height", ro;
+    /// Frame rate as reported by the container.
+    ContainerFps: f64 = "container-fps", ro;
+    /// Frame rate estimated from the video timestamps.
+    EstimatedVfFps: f64 = "estimated-vf-fps", ro;
+    /// Estimated number of frames in the current file.
+    EstimatedFrameCount: i64 = "estimated-frame-count", ro;
+    /// Number of the currently displayed frame.
+    EstimatedFrameNumber: i64 = "estimated-frame-number", ro;
+    /// Whether the video is displayed fullscreen.
+    Fullscreen: bool = "fullscreen", rw;
+    /// Whether the window stays on top of other windows.
+    Ontop: bool = "ontop", rw;
+    /// The hardware decoding API to use, e.g. `"auto"` or `"no"`.
+    Hwdec: String = "hwdec", rw;
+    /// The hardware decoding API in use, or `"no"`.
+    HwdecCurrent: String = "hwdec-current", ro;
+
+    // --- Subtitles ---
+    /// The selected subtitle track, e.g. `"1"`, `"auto"` or `"no"`.
+    Sid: String = "sid", rw;
+    /// Subtitle delay in seconds.
+    SubDelay: f64 = "sub-delay", rw;
+    /// Whether subtitles are shown.
+    SubVisibility: bool = "sub-visibility", rw;
+    /// The text of the currently displayed subtitle.
+    SubText: String = "sub-text", ro;
+
+    // --- Tracks ---
+    /// All tracks of the current file.
+    TrackList: Node = "track-list", ro;
+    /// Number of tracks of the current file.
+    TrackListCount: i64 = "track-list/count", ro;
+
+    // --- Playlist ---
+    /// All playlist entries.
+    PlaylistProp: Node = "playlist", ro;
+    /// Number of playlist entries.
+    PlaylistCount: i64 = "playlist-count", ro;
+    /// Index of the current playlist entry, or `-1`. Setting it starts playback of that entry.
+    PlaylistPos: i64 = "playlist-pos", rw;
+    /// Index of the playlist entry that is playing, or `-1`.
+    PlaylistPlayingPos: i64 = "playlist-playing-pos", ro;
+
+    // --- Chapters and editions ---
+    /// Index of the current chapter, or `-1`. Setting it seeks to that chapter.
+    ChapterProp: i64 = "chapter", rw;
+    /// Number of chapters.
+    Chapters: i64 = "chapters", ro;
+    /// All chapters of the current file.
+    ChapterList: Node = "chapter-list", rw;
+    /// Index of the current edition.
+    CurrentEdition: i64 = "current-edition", ro;
+    /// The edition to play, e.g. `"auto"` or a number. Setting it reloads the file.
+    EditionProp: String = "edition", rw;
+    /// Number of editions.
+    Editions: i64 = "editions", ro;
+    /// All editions of the current file.
+    EditionList: Node = "edition-list", ro;
+
+    // --- Miscellaneous ---
+    /// Options that only apply to the current file, as map.
+    FileLocalOptions: Node = "file-local-options", ro;
+    /// Key-value options of scripts.
+    ScriptOpts: Node = "script-opts", rw;
+    /// Whether the on screen controller is enabled.
+    Osc: bool = "osc", rw;
+    /// The mpv version.
+    MpvVersion: String = "mpv-version", ro;
+}
+
+indexed_properties! {
+    /// ID of a track, unique per track type.
+    TrackListId: i64 = "track-list" / "id", ro;
+    /// Type of a track, `"video"`, `"audio"` or `"sub"`.
+    TrackListType: String = "track-list" / "type", ro;
+    /// Title of a track.
+    TrackListTitle: String = "track-list" / "title", ro;
+    /// Language of a track.
+    TrackListLang: String = "track-list" / "lang", ro;
+    /// Whether a track is selected.
+    TrackListSelected: bool = "track-list" / "selected", ro;
+    /// Whether a track is loaded from an external file.
+    TrackListExternal: bool = "track-list" / "external", ro;
+    /// Codec of a track.
+    TrackListCodec: String = "track-list" / "codec", ro;
+
+    /// File name of a playlist entry.
+    PlaylistFilename: String = "playlist" / "filename", ro;
+    /// Title of a playlist entry.
+    PlaylistTitle: String = "playlist" / "title", ro;
+    /// ID of a playlist entry.
+    PlaylistId: i64 = "playlist" / "id", ro;
+    /// Whether a playlist entry is the current one.
+    PlaylistCurrent: bool = "playlist" / "current", ro;
+
+    /// Title of a chapter.
+    ChapterListTitle: String = "chapter-list" / "title", ro;
+    /// Start time of a chapter in seconds.
+    ChapterListTime: f64 = "chapter-list" / "time", ro;
+
+    /// ID of an edition.
+    EditionListId: i64 = "edition-list" / "id", ro;
+    /// Title of an edition.
+    EditionListTitle: String = "edition-list" / "title", ro;
+}
@@ -0,0 +1,236 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of libmpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::ptr::NonNull;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Condvar, Mutex,
+};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+type OnReply = Box<dyn FnOnce(&libmpv_sys::mpv_event) + Send>;
+
+/// Bookkeeping for asynchronous requests whose replies are delivered by `EventContext::wait_event`.
+pub(crate) struct PendingReplies {
+    next_id: AtomicU64,
+    pending: Mutex<HashMap<u64, OnReply>>,
+}
+
+impl PendingReplies {
+    pub(crate) fn new() -> PendingReplies {
+        PendingReplies {
+            next_id: AtomicU64::new(1),
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Allocate a fresh `reply_userdata`, and call `on_reply` once its reply event arrives.
+    pub(crate) fn register<F>(&self, on_reply: F) -> u64
+    where
+        F: FnOnce(&libmpv_sys::mpv_event) + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.pending.lock().unwrap().insert(id, Box::new(on_reply));
+        id
+    }
+
+    /// Forget about `id`, used if issuing the request itself failed.
+    pub(crate) fn unregister(&self, id: u64) {
+        self.pending.lock().unwrap().remove(&id);
+    }
+
+    /// Hand a reply event to whoever registered its `reply_userdata`.
+    pub(crate) fn resolve(&self, event: &libmpv_sys::mpv_event) {
+        let on_reply = self.pending.lock().unwrap().remove(&event.reply_userdata);
+        if let Some(on_reply) = on_reply {
+            on_reply(event);
+        }
+    }
+}
+
+struct SlotState<T> {
+    result: Option<Result<T>>,
+    waker: Option<Waker>,
+}
+
+/// A result that is delivered by the event thread, and waited for or polled by its handle.
+pub(crate) struct ReplySlot<T> {
+    state: Mutex<SlotState<T>>,
+    ready: Condvar,
+}
+
+impl<T> ReplySlot<T> {
+    pub(crate) fn new() -> Arc<ReplySlot<T>> {
+        Arc::new(ReplySlot {
+            state: Mutex::new(SlotState {
+                result: None,
+                waker: None,
+            }),
+            ready: Condvar::new(),
+        })
+    }
+
+    pub(crate) fn fulfill(&self, result: Result<T>) {
+        let mut state = self.state.lock().unwrap();
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        self.ready.notify_all();
+    }
+
+    pub(crate) fn wait(&self) -> Result<T> {
+        let mut state = self.state.lock().unwrap();
+        while state.result.is_none() {
+            state = self.ready.wait(state).unwrap();
+        }
+        state.result.take().unwrap()
+    }
+
+    pub(crate) fn poll(&self, cx: &mut Context<'_>) -> Poll<Result<T>> {
+        let mut state = self.state.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T: Clone> ReplySlot<T> {
+    pub(crate) fn try_result(&self) -> Option<Result<T>> {
+        self.state.lock().unwrap().result.clone()
+    }
+
+    pub(crate) fn wait_timeout(&self, timeout: Duration) -> Option<Result<T>> {
+        let state = self.state.lock().unwrap();
+        let (state, _) = self
+            .ready
+            .wait_timeout_while(state, timeout, |state| state.result.is_none())
+            .unwrap();
+        state.result.clone()
+    }
+}
+
+/// Handle to an asynchronous request, started by `Mpv::command_async`,
+/// `Mpv::command_node_async`, `Mpv::get_property_async` or `Mpv::set_property_async`.
+///
+/// The request completes once its reply event has been received by `EventContext::wait_event`,
+/// so some thread has to keep waiting for events while this is pending. The result can be
+/// polled, waited for, or awaited, since this is also a `Future`. Dropping the handle does not
+/// cancel the request.
+pub struct AsyncReply<'parent, T> {
+    ctx: NonNull<libmpv_sys::mpv_handle>,
+    reply_userdata: u64,
+    slot: Arc<ReplySlot<T>>,
+    _does_not_outlive: PhantomData<&'parent Mpv>,
+}
+
+/// Handle to a command started by `Mpv::command_async`.
+pub type AsyncCommand<'parent> = AsyncReply<'parent, ()>;
+
+unsafe impl<'parent, T: Send> Send for AsyncReply<'parent, T> {}
+unsafe impl<'parent, T: Send> Sync for AsyncReply<'parent, T> {}
+
+impl<'parent, T: Send + 'static> AsyncReply<'parent, T> {
+    /// Register a reply, whose data is extracted by `convert`, and issue the request with its
+    /// `reply_userdata`. Errors of both are passed through `context`.
+    pub(crate) fn start<C, X, F>(
+        mpv: &'parent Mpv,
+        convert: C,
+        context: X,
+        issue: F,
+    ) -> Result<AsyncReply<'parent, T>>
+    where
+        C: FnOnce(&libmpv_sys::mpv_event) -> Result<T> + Send + 'static,
+        X: Fn(Error) -> Error + Send + Sync + 'static,
+        F: FnOnce(u64) -> Result<()>,
+    {
+        let slot = ReplySlot::new();
+        let context = Arc::new(context);
+        let reply_slot = slot.clone();
+        let reply_context = context.clone();
+        let reply_userdata = mpv.replies.register(move |event| {
+            let result = mpv_err((), event.error).and_then(|()| convert(event));
+            reply_slot.fulfill(result.map_err(|e| reply_context(e)))
+        });
+
+        if let Err(e) = issue(reply_userdata) {
+            mpv.replies.unregister(reply_userdata);
+            return Err(context(e));
+        }
+
+        Ok(AsyncReply {
+            ctx: mpv.ctx,
+            reply_userdata,
+            slot,
+            _does_not_outlive: PhantomData,
+        })
+    }
+}
+
+impl<'parent, T> AsyncReply<'parent, T> {
+    /// The `reply_userdata` the corresponding reply event will carry.
+    pub fn reply_userdata(&self) -> u64 {
+        self.reply_userdata
+    }
+
+    /// Ask mpv to abort the command as soon as possible. The command still completes as usual,
+    /// but possibly with some work not done. Not all commands support this, and property
+    /// requests can not be aborted.
+    pub fn abort(&self) {
+        unsafe { libmpv_sys::mpv_abort_async_command(self.ctx.as_ptr(), self.reply_userdata) }
+    }
+
+    /// Block until the request has completed.
+    ///
+    /// This will deadlock if it is called on the thread that is supposed to call
+    /// `EventContext::wait_event`.
+    pub fn wait(self) -> Result<T> {
+        self.slot.wait()
+    }
+}
+
+impl<'parent, T: Clone> AsyncReply<'parent, T> {
+    /// Return the result of the request, if it has completed yet.
+    pub fn try_result(&self) -> Option<Result<T>> {
+        self.slot.try_result()
+    }
+
+    /// Block until the request has completed, or `timeout` has passed.
+    pub fn wait_timeout(&self, timeout: Duration) -> Option<Result<T>> {
+        self.slot.wait_timeout(timeout)
+    }
+}
+
+impl<'parent, T> Future for AsyncReply<'parent, T> {
+    type Output = Result<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T>> {
+        self.slot.poll(cx)
+    }
+}
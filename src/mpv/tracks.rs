@@ -0,0 +1,236 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of libmpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+
+use std::convert::TryFrom;
+
+/// The type of a track.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TrackType {
+    Video,
+    Audio,
+    Sub,
+}
+
+impl TrackType {
+    const ALL: [TrackType; 3] = [TrackType::Video, TrackType::Audio, TrackType::Sub];
+
+    /// The property that selects the track of this type, `vid`, `aid` or `sid`.
+    pub fn property(self) -> &'static str {
+        match self {
+            TrackType::Video => "vid",
+            TrackType::Audio => "aid",
+            TrackType::Sub => "sid",
+        }
+    }
+
+    fn from_str(name: &str) -> Option<TrackType> {
+        match name {
+            "video" => Some(TrackType::Video),
+            "audio" => Some(TrackType::Audio),
+            "sub" => Some(TrackType::Sub),
+            _ => None,
+        }
+    }
+}
+
+/// A track of the current file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Track {
+    /// The ID of this track, which is unique per track type.
+    pub id: i64,
+    pub track_type: TrackType,
+    pub title: Option<String>,
+    pub lang: Option<String>,
+    pub codec: Option<String>,
+    pub default: bool,
+    pub forced: bool,
+    /// Whether this track was loaded from a separate file, e.g. by `sub-add`.
+    pub external: bool,
+    /// The file an external track was loaded from.
+    pub external_filename: Option<String>,
+    pub selected: bool,
+}
+
+impl Track {
+    fn eq_except_selected(&self, other: &Track) -> bool {
+        let selected = Track {
+            selected: other.selected,
+            ..self.clone()
+        };
+        selected == *other
+    }
+}
+
+/// A snapshot of the `track-list` property.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Tracks {
+    pub tracks: Vec<Track>,
+}
+
+/// A change between two track list snapshots, as computed by `Diff::diff`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TrackChange {
+    /// `track` was added.
+    Added(Track),
+    /// The track `id` of `track_type` was removed.
+    Removed { track_type: TrackType, id: i64 },
+    /// The track `id` of `track_type` was selected, or none if `id` is `None`.
+    Selected {
+        track_type: TrackType,
+        id: Option<i64>,
+    },
+    /// Properties of `track`, other than whether it is selected, changed.
+    Updated(Track),
+}
+
+impl Tracks {
+    /// The tracks of `track_type`.
+    pub fn of_type(&self, track_type: TrackType) -> impl Iterator<Item = &Track> {
+        self.tracks
+            .iter()
+            .filter(move |track| track.track_type == track_type)
+    }
+
+    /// The selected track of `track_type`, if any.
+    pub fn selected(&self, track_type: TrackType) -> Option<&Track> {
+        self.of_type(track_type).find(|track| track.selected)
+    }
+
+    /// The track `id` of `track_type`.
+    pub fn get(&self, track_type: TrackType, id: i64) -> Option<&Track> {
+        self.of_type(track_type).find(|track| track.id == id)
+    }
+}
+
+impl Diff for Tracks {
+    type Change = TrackChange;
+
+    /// Compute the changes between `self` and `new`. Tracks are matched by their type and ID.
+    fn diff(&self, new: &Tracks) -> Vec<TrackChange> {
+        let mut changes = Vec::new();
+
+        for track in &self.tracks {
+            if new.get(track.track_type, track.id).is_none() {
+                changes.push(TrackChange::Removed {
+                    track_type: track.track_type,
+                    id: track.id,
+                });
+            }
+        }
+        for track in &new.tracks {
+            match self.get(track.track_type, track.id) {
+                None => changes.push(TrackChange::Added(track.clone())),
+                Some(old) if !old.eq_except_selected(track) => {
+                    changes.push(TrackChange::Updated(track.clone()))
+                }
+                Some(_) => {}
+            }
+        }
+        for &track_type in TrackType::ALL.iter() {
+            let id = new.selected(track_type).map(|track| track.id);
+            if self.selected(track_type).map(|track| track.id) != id {
+                changes.push(TrackChange::Selected { track_type, id });
+            }
+        }
+
+        changes
+    }
+}
+
+impl TryFrom<Node> for Track {
+    type Error = Error;
+
+    fn try_from(node: Node) -> Result<Track> {
+        let format_err = || Error::Raw(mpv_error::PropertyFormat);
+        let string = |key| node.get(key).and_then(Node::as_str).map(str::to_owned);
+        let flag = |key| node.get(key).and_then(Node::as_bool).unwrap_or(false);
+        Ok(Track {
+            id: node
+                .get("id")
+                .and_then(Node::as_i64)
+                .ok_or_else(format_err)?,
+            track_type: node
+                .get("type")
+                .and_then(Node::as_str)
+                .and_then(TrackType::from_str)
+                .ok_or_else(format_err)?,
+            title: string("title"),
+            lang: string("lang"),
+            codec: string("codec"),
+            default: flag("default"),
+            forced: flag("forced"),
+            external: flag("external"),
+            external_filename: string("external-filename"),
+            selected: flag("selected"),
+        })
+    }
+}
+
+impl TryFrom<Node> for Tracks {
+    type Error = Error;
+
+    fn try_from(node: Node) -> Result<Tracks> {
+        let tracks = match node {
+            Node::Array(tracks) => tracks,
+            _ => return Err(Error::Raw(mpv_error::PropertyFormat)),
+        };
+        let tracks = tracks
+            .into_iter()
+            // Tracks of other types, e.g. `image`, are not supported.
+            .filter(|track| {
+                track
+                    .get("type")
+                    .and_then(Node::as_str)
+                    .and_then(TrackType::from_str)
+                    .is_some()
+            })
+            .map(Track::try_from)
+            .collect::<Result<_>>()?;
+        Ok(Tracks { tracks })
+    }
+}
+
+/// Watches the track list, created by `Mpv::watch_tracks`, and reports `TrackChange`s.
+pub type TracksWatcher<'parent> = DiffWatcher<'parent, Tracks>;
+
+impl Mpv {
+    /// Get a snapshot of the tracks of the current file.
+    pub fn tracks(&self) -> Result<Tracks> {
+        self.get_node_property("track-list")
+    }
+
+    /// Watch the track list. The first update reports the tracks that already exist as added.
+    pub fn watch_tracks(&self) -> Result<TracksWatcher<'_>> {
+        self.watch_diff("track-list")
+    }
+
+    /// Select the track `id` of `track_type`, by setting `vid`, `aid` or `sid`.
+    ///
+    /// This is a method of `Mpv` rather than `Tracks`, since `Tracks` is a snapshot that is not
+    /// updated by selecting a track; use `Mpv::tracks` or `Mpv::watch_tracks` to see the effect.
+    pub fn select_track(&self, track_type: TrackType, id: i64) -> Result<()> {
+        self.set_property(track_type.property(), id)
+    }
+
+    /// Select no track of `track_type`, e.g. to hide subtitles.
+    pub fn deselect_track(&self, track_type: TrackType) -> Result<()> {
+        self.set_property(track_type.property(), "no")
+    }
+}
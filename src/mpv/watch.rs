@@ -0,0 +1,299 @@
+// Copyright (C) 2016  ParadoxSpiral
+//
+// This file is part of libmpv-rs.
+//
+// This library is free software; you can redistribute it and/or
+// modify it under the terms of the GNU Lesser General Public
+// License as published by the Free Software Foundation; either
+// version 2.1 of the License, or (at your option) any later version.
+//
+// This library is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the GNU
+// Lesser General Public License for more details.
+//
+// You should have received a copy of the GNU Lesser General Public
+// License along with this library; if not, write to the Free Software
+// Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
+
+use super::*;
+
+use self::events::{OwnedPropertyData, PropertyData};
+
+use std::collections::HashMap;
+use std::convert::{TryFrom, TryInto};
+use std::ffi::CString;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Condvar, Mutex,
+};
+use std::time::{Duration, Instant};
+
+/// Observation ids with this bit set are allocated by `Mpv::watch`.
+const WATCH_ID_BASE: u64 = 1 << 63;
+
+type OnChange = Box<dyn Fn(&libmpv_sys::mpv_event_property) + Send>;
+
+/// The watchers of an `Mpv`, which are updated by `EventContext::wait_event`.
+pub(crate) struct Watchers {
+    next_id: AtomicU64,
+    watchers: Mutex<HashMap<u64, OnChange>>,
+}
+
+impl Watchers {
+    pub(crate) fn new() -> Watchers {
+        Watchers {
+            next_id: AtomicU64::new(WATCH_ID_BASE),
+            watchers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn register(&self, on_change: OnChange) -> u64 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.watchers.lock().unwrap().insert(id, on_change);
+        id
+    }
+
+    fn unregister(&self, id: u64) {
+        self.watchers.lock().unwrap().remove(&id);
+    }
+
+    /// Hand an `MPV_EVENT_PROPERTY_CHANGE` to the watcher that observes it, if any.
+    pub(crate) fn dispatch(&self, event: &libmpv_sys::mpv_event) {
+        if event.reply_userdata & WATCH_ID_BASE == 0 {
+            return;
+        }
+        let watchers = self.watchers.lock().unwrap();
+        if let Some(on_change) = watchers.get(&event.reply_userdata) {
+            on_change(unsafe { &*(event.data as *const libmpv_sys::mpv_event_property) });
+        }
+    }
+}
+
+struct WatchState<T> {
+    update: Option<Result<Option<T>>>,
+    last_delivered: Option<Instant>,
+}
+
+struct WatchSlot<T> {
+    state: Mutex<WatchState<T>>,
+    changed: Condvar,
+}
+
+/// A typed observer of a property, created by `Mpv::watch`.
+///
+/// Only the latest update is kept: if the property changes multiple times before it is received,
+/// the intermediate values are skipped. Updates are only received while some thread keeps
+/// calling `EventContext::wait_event`. The property is unobserved when the watcher is dropped.
+pub struct PropertyWatcher<'parent, T> {
+    mpv: &'parent Mpv,
+    id: u64,
+    slot: Arc<WatchSlot<T>>,
+    interval: Option<Duration>,
+}
+
+impl Mpv {
+    /// Observe the property `name`, with an observation id that is allocated internally.
+    ///
+    /// The returned watcher yields `Some(value)` whenever the property changes, and `None` if it
+    /// becomes unavailable, starting with its current value. The changes are also returned as
+    /// `Event::PropertyChange` by `EventContext::wait_event`, with a `reply_userdata` that has
+    /// its highest bit set; such ids should not be used with `EventContext::observe_property`.
+    pub fn watch<T>(&self, name: &str) -> Result<PropertyWatcher<'_, T>>
+    where
+        T: GetData + TryFrom<OwnedPropertyData, Error = Error> + Send + 'static,
+    {
+        self.watch_with(name, T::get_format(), |data| data.try_into().map(Some))
+    }
+
+    /// Watch the property `name` for changes, and compute how its value changed with `T::diff`.
+    /// The value is read as `Node`, and converted with `TryFrom`.
+    pub fn watch_diff<T>(&self, name: &str) -> Result<DiffWatcher<'_, T>>
+    where
+        T: Diff + TryFrom<Node, Error = Error> + Send + 'static,
+    {
+        Ok(DiffWatcher {
+            watcher: self.watch_with(name, Format::Node, |data| {
+                Node::try_from(data)?.try_into().map(Some)
+            })?,
+            current: T::default(),
+        })
+    }
+
+    /// Like `watch`, but the value is observed as `format`, and converted by `convert`, which
+    /// may map it to `None` like an unavailable property.
+    pub(crate) fn watch_with<T, F>(
+        &self,
+        name: &str,
+        format: Format,
+        convert: F,
+    ) -> Result<PropertyWatcher<'_, T>>
+    where
+        T: Send + 'static,
+        F: Fn(OwnedPropertyData) -> Result<Option<T>> + Send + 'static,
+    {
+        let c_name = CString::new(name)?;
+        let context = name.to_owned();
+        let slot = Arc::new(WatchSlot {
+            state: Mutex::new(WatchState {
+                update: None,
+                last_delivered: None,
+            }),
+            changed: Condvar::new(),
+        });
+
+        let watch_slot = slot.clone();
+        let id = self.watchers.register(Box::new(move |property| {
+            let update = if property.format == mpv_format::None {
+                Ok(None)
+            } else {
+                // SAFETY: safe because we are passing format + data from an mpv_event_property
+                unsafe { PropertyData::from_raw(property.format, property.data) }
+                    .and_then(|data| data.to_owned_data())
+                    .and_then(&convert)
+            };
+            watch_slot.state.lock().unwrap().update =
+                Some(update.map_err(|e| e.for_property(&context)));
+            watch_slot.changed.notify_all();
+        }));
+
+        let ret = mpv_err((), unsafe {
+            libmpv_sys::mpv_observe_property(
+                self.ctx.as_ptr(),
+                id,
+                c_name.as_ptr(),
+                format.as_mpv_format() as _,
+            )
+        });
+        if let Err(e) = ret {
+            self.watchers.unregister(id);
+            return Err(e.for_property(name));
+        }
+
+        Ok(PropertyWatcher {
+            mpv: self,
+            id,
+            slot,
+            interval: None,
+        })
+    }
+}
+
+impl<'parent, T> PropertyWatcher<'parent, T> {
+    /// Receive at most one update per `interval`. Changes in between are coalesced into the
+    /// latest value, which is useful for high-frequency properties such as `time-pos`.
+    pub fn rate_limit(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Return the latest update, if there is one that has not been received yet.
+    pub fn try_recv(&self) -> Option<Result<Option<T>>> {
+        self.recv_until(Some(Instant::now()))
+    }
+
+    /// Block until the property changes. Like `AsyncReply::wait`, this must not be called on
+    /// the thread that calls `EventContext::wait_event`.
+    pub fn recv(&self) -> Result<Option<T>> {
+        self.recv_until(None)
+            .expect("waiting without deadline returned nothing")
+    }
+
+    /// Block until the property changes, or `timeout` has passed.
+    pub fn recv_timeout(&self, timeout: Duration) -> Option<Result<Option<T>>> {
+        self.recv_until(Some(Instant::now() + timeout))
+    }
+
+    fn recv_until(&self, deadline: Option<Instant>) -> Option<Result<Option<T>>> {
+        let mut state = self.slot.state.lock().unwrap();
+        loop {
+            let now = Instant::now();
+            let ready_at = match (state.last_delivered, self.interval) {
+                (Some(last), Some(interval)) if last + interval > now => Some(last + interval),
+                _ => None,
+            };
+
+            if state.update.is_some() && ready_at.is_none() {
+                state.last_delivered = Some(now);
+                return state.update.take();
+            }
+            if matches!(deadline, Some(deadline) if deadline <= now) {
+                return None;
+            }
+
+            // Wake up once the next update may be delivered, or the deadline passes.
+            let wake_at = match (ready_at.filter(|_| state.update.is_some()), deadline) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            state = match wake_at {
+                Some(wake_at) => {
+                    self.slot
+                        .changed
+                        .wait_timeout(state, wake_at - now)
+                        .unwrap()
+                        .0
+                }
+                None => self.slot.changed.wait(state).unwrap(),
+            };
+        }
+    }
+}
+
+impl<'parent, T> Drop for PropertyWatcher<'parent, T> {
+    fn drop(&mut self) {
+        unsafe { libmpv_sys::mpv_unobserve_property(self.mpv.ctx.as_ptr(), self.id) };
+        self.mpv.watchers.unregister(self.id);
+    }
+}
+
+/// A snapshot of a property that can compute how it changed, as reported by `DiffWatcher`.
+pub trait Diff: Default {
+    /// A single change between two snapshots.
+    type Change;
+
+    /// Compute the changes between `self` and `new`.
+    fn diff(&self, new: &Self) -> Vec<Self::Change>;
+}
+
+/// Watches a property, created by `Mpv::watch_diff`, and reports how its value changed since the
+/// last received one.
+///
+/// The first update is compared to the empty `T::default()`, and an unavailable property counts
+/// as empty too.
+pub struct DiffWatcher<'parent, T> {
+    watcher: PropertyWatcher<'parent, T>,
+    current: T,
+}
+
+impl<'parent, T: Diff> DiffWatcher<'parent, T> {
+    /// The latest received value.
+    pub fn current(&self) -> &T {
+        &self.current
+    }
+
+    /// Return the changes since the last received value, if the property changed since.
+    pub fn try_recv(&mut self) -> Option<Result<Vec<T::Change>>> {
+        let update = self.watcher.try_recv()?;
+        Some(self.apply(update))
+    }
+
+    /// Block until the property changes, and return the changes, see `PropertyWatcher::recv`.
+    pub fn recv(&mut self) -> Result<Vec<T::Change>> {
+        let update = self.watcher.recv();
+        self.apply(update)
+    }
+
+    /// Block until the property changes, or `timeout` has passed.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Option<Result<Vec<T::Change>>> {
+        let update = self.watcher.recv_timeout(timeout)?;
+        Some(self.apply(update))
+    }
+
+    fn apply(&mut self, update: Result<Option<T>>) -> Result<Vec<T::Change>> {
+        let new = update?.unwrap_or_default();
+        let changes = self.current.diff(&new);
+        self.current = new;
+        Ok(changes)
+    }
+}
@@ -16,7 +16,7 @@
 // License along with this library; if not, write to the Free Software
 // Foundation, Inc., 51 Franklin Street, Fifth Floor, Boston, MA  02110-1301  USA
 
-use crate::events::{Event, PropertyData};
+use crate::events::{mpv_event_id, Event, OwnedEventKind, OwnedPropertyData, PropertyData};
 use crate::*;
 
 use std::collections::HashMap;
@@ -34,9 +34,12 @@ fn initializer() {
     })
     .unwrap();
 
-    assert_eq!(true, mpv.get_property("osc").unwrap());
-    assert_eq!(true, mpv.get_property("input-default-bindings").unwrap());
-    assert_eq!(30i64, mpv.get_property("volume").unwrap());
+    assert_eq!(true, mpv.get_property::<bool>("osc").unwrap());
+    assert_eq!(
+        true,
+        mpv.get_property::<bool>("input-default-bindings").unwrap()
+    );
+    assert_eq!(30i64, mpv.get_property::<i64>("volume").unwrap());
 }
 
 #[test]
@@ -47,10 +50,10 @@ fn properties() {
     mpv.set_property("ytdl-format", "best[width<240]").unwrap();
     mpv.set_property("sub-gauss", 0.6).unwrap();
 
-    assert_eq!(0i64, mpv.get_property("volume").unwrap());
+    assert_eq!(0i64, mpv.get_property::<i64>("volume").unwrap());
     let vo: MpvStr = mpv.get_property("vo").unwrap();
     assert_eq!("null", &*vo);
-    assert_eq!(true, mpv.get_property("ytdl").unwrap());
+    assert_eq!(true, mpv.get_property::<bool>("ytdl").unwrap());
     let subg: f64 = mpv.get_property("sub-gauss").unwrap();
     assert_eq!(
         0.6,
@@ -108,6 +111,15 @@ fn events() {
             reply_userdata: 0,
         })
     );
+    assert_event_occurs!(
+        ev_ctx,
+        3.,
+        Ok(Event::PropertyChange {
+            name: "media-title",
+            change: PropertyData::Unavailable,
+            reply_userdata: 1,
+        })
+    );
 
     mpv.set_property("volume", 0).unwrap();
     assert_event_occurs!(
@@ -127,7 +139,7 @@ fn events() {
         None,
     )])
     .unwrap();
-    assert_event_occurs!(ev_ctx, 10., Ok(Event::StartFile));
+    assert_event_occurs!(ev_ctx, 10., Ok(Event::StartFile { .. }));
     assert_event_occurs!(
         ev_ctx,
         10.,
@@ -138,6 +150,15 @@ fn events() {
         })
     );
     assert_event_occurs!(ev_ctx, 20., Err(Error::Raw(mpv_error::UnknownFormat)));
+    assert_event_occurs!(
+        ev_ctx,
+        3.,
+        Ok(Event::PropertyChange {
+            name: "media-title",
+            change: PropertyData::Unavailable,
+            reply_userdata: 1,
+        })
+    );
     assert!(ev_ctx.wait_event(3.).is_none());
 
     mpv.playlist_load_files(&[(
@@ -146,7 +167,7 @@ fn events() {
         None,
     )])
     .unwrap();
-    assert_event_occurs!(ev_ctx, 10., Ok(Event::StartFile));
+    assert_event_occurs!(ev_ctx, 10., Ok(Event::StartFile { .. }));
     assert_event_occurs!(
         ev_ctx,
         3.,
@@ -164,6 +185,56 @@ fn events() {
     assert!(ev_ctx.wait_event(3.).is_none());
 }
 
+#[test]
+fn property_unavailable() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_all_events()?;
+    ev_ctx.enable_event(mpv_event_id::PropertyChange)?;
+    ev_ctx.observe_property("path", Format::String, 7)?;
+
+    assert_event_occurs!(
+        ev_ctx,
+        3.,
+        Ok(Event::PropertyChange {
+            name: "path",
+            change: PropertyData::Unavailable,
+            reply_userdata: 7,
+        })
+    );
+
+    mpv.playlist_load_files(&[(
+        "test-data/speech_12kbps_mb.wav",
+        FileState::AppendPlay,
+        None,
+    )])?;
+    assert_event_occurs!(
+        ev_ctx,
+        3.,
+        Ok(Event::PropertyChange {
+            name: "path",
+            change: PropertyData::Str("test-data/speech_12kbps_mb.wav"),
+            reply_userdata: 7,
+        })
+    );
+
+    mpv.command_argv("stop", &[])?;
+    assert_event_occurs!(
+        ev_ctx,
+        3.,
+        Ok(Event::PropertyChange {
+            name: "path",
+            change: PropertyData::Unavailable,
+            reply_userdata: 7,
+        })
+    );
+
+    ev_ctx.unobserve_property(7)?;
+    assert!(ev_ctx.wait_event(1.).is_none());
+
+    Ok(())
+}
+
 #[test]
 fn node_map() -> Result<()> {
     let mpv = Mpv::new()?;
@@ -176,7 +247,7 @@ fn node_map() -> Result<()> {
 
     thread::sleep(Duration::from_millis(250));
     let audio_params: MpvNode = mpv.get_property("audio-params")?;
-    let params: HashMap<&str, MpvNode> =
+    let params: HashMap<&str, MpvNodeRef> =
         audio_params.to_map().ok_or_else(|| Error::Null)?.collect();
 
     assert_eq!(params.len(), 5);
@@ -211,10 +282,10 @@ fn node_array() -> Result<()> {
 
     thread::sleep(Duration::from_millis(250));
     let playlist: MpvNode = mpv.get_property("playlist")?;
-    let items: Vec<MpvNode> = playlist.to_array().ok_or_else(|| Error::Null)?.collect();
+    let items: Vec<MpvNodeRef> = playlist.to_array().ok_or_else(|| Error::Null)?.collect();
 
     assert_eq!(items.len(), 1);
-    let track: HashMap<&str, MpvNode> = items[0].to_map().ok_or_else(|| Error::Null)?.collect();
+    let track: HashMap<&str, MpvNodeRef> = items[0].to_map().ok_or_else(|| Error::Null)?.collect();
 
     let filename = track.get("filename").unwrap().value()?;
 
@@ -225,3 +296,820 @@ fn node_array() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn command_async() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+
+    let cmd = mpv.command_async("loadfile", &["test-data/speech_12kbps_mb.wav"])?;
+    assert!(cmd.try_result().is_none());
+
+    assert_event_occurs!(ev_ctx, 3., Ok(Event::CommandReply { .. }));
+    assert_eq!(cmd.try_result(), Some(Ok(())));
+
+    Ok(())
+}
+
+#[test]
+fn command_node_async() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+
+    let cmd = mpv.command_node_async(Node::from(vec!["expand-text", "${volume}"]))?;
+    assert_event_occurs!(ev_ctx, 3., Ok(Event::CommandReply { .. }));
+    assert_eq!(cmd.try_result(), Some(Ok(Node::from("100"))));
+
+    Ok(())
+}
+
+#[test]
+fn command_argv() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let path = "test-data/a \"quoted\" \\ name\n.wav";
+
+    mpv.playlist_load_files(&[(path, FileState::Append, None)])?;
+
+    let filename: String = mpv.get_property("playlist/0/filename")?;
+    assert_eq!(filename, path);
+
+    Ok(())
+}
+
+#[test]
+fn command_ret() -> Result<()> {
+    let mpv = Mpv::new()?;
+    mpv.set_property("volume", 42)?;
+
+    assert_eq!(mpv.expand_text("vol: ${volume}")?, "vol: 42");
+
+    let first =
+        mpv.playlist_load_file("test-data/speech_12kbps_mb.wav", FileState::Append, None)?;
+    let second =
+        mpv.playlist_load_file("test-data/speech_12kbps_mb.wav", FileState::Append, None)?;
+    assert!(second > first);
+
+    let echo = mpv.subprocess(&["echo", "hello"], false)?;
+    assert_eq!(echo.status, 0);
+    assert_eq!(echo.stdout, b"hello\n");
+
+    Ok(())
+}
+
+#[test]
+fn clients() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let ui = mpv.create_client("ui")?;
+    let weak = mpv.create_weak_client("analytics")?;
+
+    assert_eq!(mpv.client_name()?, "main");
+    assert_eq!(ui.client_name()?, "ui");
+    assert_eq!(weak.client_name()?, "analytics");
+    assert_ne!(mpv.client_id(), ui.client_id());
+
+    let mut ui_ctx = ui.create_event_context();
+    ui_ctx.disable_deprecated_events()?;
+    ui_ctx.observe_property("volume", Format::Int64, 7)?;
+    mpv.set_property("volume", 33)?;
+
+    assert_event_occurs!(
+        ui_ctx,
+        3.,
+        Ok(Event::PropertyChange {
+            name: "volume",
+            change: PropertyData::Int64(33),
+            reply_userdata: 7,
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+fn hooks() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+    ev_ctx.hook_add("on_load", 0, 42)?;
+
+    mpv.playlist_load_files(&[(
+        "test-data/speech_12kbps_mb.wav",
+        FileState::AppendPlay,
+        None,
+    )])?;
+    let guard = loop {
+        match ev_ctx.wait_event(3.) {
+            Some(Ok(Event::Hook {
+                name: "on_load",
+                reply_userdata: 42,
+                guard,
+            })) => break guard,
+            None => continue,
+            other => panic!("Event did not occur, got: {:?}", other),
+        }
+    };
+
+    // The hook stays open while other events are waited for.
+    let filename = mpv.get_property_async::<String>("stream-open-filename")?;
+    assert_event_occurs!(ev_ctx, 3., Ok(Event::GetPropertyReply { .. }));
+    assert_eq!(
+        filename.try_result(),
+        Some(Ok("test-data/speech_12kbps_mb.wav".to_owned()))
+    );
+
+    guard.resume();
+    assert_event_occurs!(ev_ctx, 3., Ok(Event::FileLoaded));
+
+    Ok(())
+}
+
+#[test]
+fn log_messages() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+    ev_ctx.request_log_messages(mpv_log_level::V)?;
+
+    mpv.command_argv("show-text", &["hello"])?;
+    assert_event_occurs!(ev_ctx, 3., Ok(Event::LogMessage { .. }));
+
+    Ok(())
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn event_stream() -> Result<()> {
+    use futures::{executor::block_on, StreamExt};
+
+    let mpv = Mpv::new()?;
+    let ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+    ev_ctx.observe_property("volume", Format::Int64, 0)?;
+
+    let mut events = ev_ctx.into_stream();
+    let ev = block_on(events.next()).unwrap()?;
+    assert_eq!(
+        ev.kind,
+        OwnedEventKind::PropertyChange {
+            name: "volume".to_owned(),
+            change: OwnedPropertyData::Int64(100),
+            reply_userdata: 0,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "futures")]
+#[test]
+fn mapped_event_stream() -> Result<()> {
+    use futures::{executor::block_on, StreamExt};
+
+    let mpv = Mpv::new()?;
+    let ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+    ev_ctx.observe_property("volume", Format::Int64, 0)?;
+
+    let mut volumes = ev_ctx.into_stream_with(|ev| match ev {
+        Ok(Event::PropertyChange {
+            change: PropertyData::Int64(volume),
+            ..
+        }) => Some(volume),
+        _ => None,
+    });
+
+    assert_eq!(block_on(volumes.next()), Some(100));
+    mpv.set_property("volume", 20)?;
+    assert_eq!(block_on(volumes.next()), Some(20));
+
+    Ok(())
+}
+
+#[test]
+fn owned_events() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+    ev_ctx.observe_property("volume", Format::Int64, 0)?;
+
+    let (tx, rx) = ::std::sync::mpsc::channel();
+    let ev = ev_ctx.wait_event(3.).unwrap()?;
+    tx.send(ev.to_owned_event(&mpv)?).unwrap();
+    let ev = thread::spawn(move || rx.recv().unwrap()).join().unwrap();
+
+    assert!(ev.time_us > 0);
+    assert_eq!(
+        ev.kind,
+        OwnedEventKind::PropertyChange {
+            name: "volume".to_owned(),
+            change: OwnedPropertyData::Int64(100),
+            reply_userdata: 0,
+        }
+    );
+
+    Ok(())
+}
+
+#[test]
+fn event_to_node() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+    ev_ctx.observe_property("volume", Format::Int64, 7)?;
+
+    let event = ev_ctx.wait_event(3.).unwrap()?;
+    let node = event.to_node()?;
+    assert_eq!(
+        node.get("event").and_then(Node::as_str),
+        Some("property-change")
+    );
+    assert_eq!(node.get("id").and_then(Node::as_i64), Some(7));
+    assert_eq!(node.get("name").and_then(Node::as_str), Some("volume"));
+    assert_eq!(node.get("data").and_then(Node::as_i64), Some(100));
+    let owned = event.to_owned_event(&mpv)?;
+    assert_eq!(owned.to_node(), node);
+
+    #[cfg(feature = "serde_json")]
+    assert_eq!(
+        owned.to_json(),
+        serde_json::json!({
+            "event": "property-change",
+            "id": 7,
+            "name": "volume",
+            "data": 100,
+        })
+    );
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+fn wakeup_pipe() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+    let fd = ev_ctx.wakeup_pipe()?;
+    ev_ctx.observe_property("volume", Format::Int64, 1)?;
+
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    assert_eq!(1, unsafe { libc::poll(&mut pollfd, 1, 3000) });
+    assert_ne!(0, pollfd.revents & libc::POLLIN);
+
+    let mut volume = None;
+    ev_ctx.drain_events(|event| {
+        if let Ok(Event::PropertyChange {
+            name: "volume",
+            change: PropertyData::Int64(value),
+            ..
+        }) = event
+        {
+            volume = Some(value);
+        }
+    })?;
+    assert_eq!(Some(100), volume);
+
+    Ok(())
+}
+
+#[test]
+fn end_file() -> Result<()> {
+    let mpv = Mpv::new()?;
+    mpv.set_property("vo", "null")?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+
+    let id = mpv.playlist_load_file("does-not-exist.mkv", FileState::Replace, None)?;
+    loop {
+        match ev_ctx.wait_event(3.) {
+            Some(Ok(Event::StartFile { playlist_entry_id })) => assert_eq!(playlist_entry_id, id),
+            Some(Ok(Event::EndFile {
+                reason,
+                error,
+                playlist_entry_id,
+                ..
+            })) => {
+                assert_eq!(reason, mpv_end_file_reason::Error);
+                assert!(error.is_some());
+                assert_eq!(playlist_entry_id, id);
+                break;
+            }
+            Some(Ok(_)) => {}
+            other => panic!("Event did not occur, got: {:?}", other),
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn node_set_data() -> Result<()> {
+    use std::convert::TryFrom;
+
+    let mpv = Mpv::new()?;
+
+    let mut opts = HashMap::new();
+    opts.insert("osc-layout", "box");
+    mpv.set_property("script-opts", Node::from(opts))?;
+    let opts = mpv.get_property::<MpvNode>("script-opts")?.to_node()?;
+    let opts = HashMap::<String, String>::try_from(opts)?;
+    assert_eq!(opts["osc-layout"], "box");
+
+    let expanded = mpv.command_node(Node::from(vec!["expand-text", "${volume}"]))?;
+    assert_eq!(expanded.to_node()?, Node::from("100"));
+
+    Ok(())
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn node_serde() -> Result<()> {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Debug, Deserialize, PartialEq, Serialize)]
+    struct Options {
+        #[serde(rename = "osc-layout")]
+        layout: String,
+        #[serde(rename = "osc-scale")]
+        scale: Option<f64>,
+    }
+
+    let opts = Options {
+        layout: "box".to_owned(),
+        scale: None,
+    };
+    let node = Node::serialize(&opts)?;
+    assert_eq!(node.get("osc-layout"), Some(&Node::from("box")));
+    assert_eq!(node.deserialize::<Options>()?, opts);
+
+    let mpv = Mpv::new()?;
+    let volume: f64 = mpv.get_property::<Node>("volume")?.deserialize()?;
+    assert_eq!(volume, 100.);
+
+    Ok(())
+}
+
+#[test]
+fn typed_properties() -> Result<()> {
+    use crate::props::{self, Property};
+
+    let mpv = Mpv::new()?;
+
+    mpv.set(props::Pause, true)?;
+    assert_eq!(mpv.get(props::Pause)?, Some(true));
+    mpv.set(props::Volume, 42.)?;
+    assert_eq!(mpv.get(props::Volume)?, Some(42.));
+    assert_eq!(mpv.get(props::TimePos)?, None);
+
+    assert_eq!(props::TrackListLang(2).name(), "track-list/2/lang");
+    assert_eq!(props::DurationProp.name(), "duration");
+
+    Ok(())
+}
+
+#[test]
+fn property_async() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+
+    let set = mpv.set_property_async("volume", 42.)?;
+    assert_event_occurs!(ev_ctx, 3., Ok(Event::SetPropertyReply(_)));
+    assert_eq!(set.try_result(), Some(Ok(())));
+
+    let volume = mpv.get_property_async::<f64>("volume")?;
+    let missing = mpv.get_property_async::<String>("does-not-exist")?;
+    assert_event_occurs!(ev_ctx, 3., Ok(Event::GetPropertyReply { .. }));
+    assert_event_occurs!(ev_ctx, 3., Err(_));
+    assert_eq!(volume.wait()?, 42.);
+    assert_eq!(
+        missing.try_result().unwrap().unwrap_err().code(),
+        Some(mpv_error::PropertyNotFound)
+    );
+
+    Ok(())
+}
+
+#[test]
+fn property_watcher() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+
+    let volume = mpv.watch::<f64>("volume")?;
+    assert_event_occurs!(ev_ctx, 3., Ok(Event::PropertyChange { name: "volume", .. }));
+    assert_eq!(volume.try_recv(), Some(Ok(Some(100.))));
+    assert_eq!(volume.try_recv(), None);
+
+    mpv.set_property("volume", 10.)?;
+    mpv.set_property("volume", 20.)?;
+    assert_event_occurs!(ev_ctx, 3., Ok(Event::PropertyChange { name: "volume", .. }));
+    while ev_ctx.wait_event(0.1).is_some() {}
+    assert_eq!(volume.recv(), Ok(Some(20.)));
+
+    Ok(())
+}
+
+#[test]
+fn error_context() -> Result<()> {
+    fn assert_send_sync<T: Send + Sync + std::error::Error + 'static>() {}
+    assert_send_sync::<Error>();
+
+    let mpv = Mpv::new()?;
+
+    let err = mpv.get_property::<i64>("does-not-exist").unwrap_err();
+    assert_eq!(err.code(), Some(mpv_error::PropertyNotFound));
+    assert_eq!(err.to_string(), "property `does-not-exist` failed");
+    let source = std::error::Error::source(&err).unwrap();
+    assert!(source.to_string().starts_with("property not found"));
+
+    let err = mpv.command_argv("does-not-exist", &["a b"]).unwrap_err();
+    assert_eq!(
+        err,
+        Error::Command {
+            args: vec!["does-not-exist".to_owned(), "a b".to_owned()],
+            error: Box::new(Error::Raw(mpv_error::InvalidParameter)),
+        }
+    );
+
+    let err = mpv
+        .playlist_load_files(&[("a.mkv", FileState::Replace, Some("bad option"))])
+        .unwrap_err();
+    assert!(matches!(err, Error::Loadfiles { index: 0, .. }));
+    assert!(err.code().is_some());
+
+    Ok(())
+}
+
+#[test]
+fn message_bus() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+    let ui = mpv.create_client("ui")?;
+    let mut ui_ctx = ui.create_event_context();
+    ui_ctx.disable_deprecated_events()?;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let _greet = ui.register_message_handler("greet", move |args| {
+        tx.send(args.join(" ")).unwrap();
+    })?;
+    assert!(ui.register_message_handler("greet", |_| ()).is_err());
+
+    mpv.script_message_to("ui", &["greet", "hello", "world"])?;
+    assert_event_occurs!(ui_ctx, 3., Ok(Event::ClientMessage(_)));
+    assert_eq!(rx.try_recv().unwrap(), "hello world");
+
+    let reply = mpv.script_request("ui", "ping", &["1"])?;
+    let (reply_to, id) = loop {
+        if let Some(Ok(Event::ClientMessage(args))) = ui_ctx.wait_event(3.) {
+            assert_eq!(args[0], "ping");
+            assert_eq!(args[3], "1");
+            break (args[1].to_owned(), args[2].to_owned());
+        }
+    };
+    ui.script_message_to(&reply_to, &[&id, "pong"])?;
+    assert_event_occurs!(ev_ctx, 3., Ok(Event::ClientMessage(_)));
+    assert_eq!(reply.try_result(), Some(Ok(vec!["pong".to_owned()])));
+
+    Ok(())
+}
+
+#[test]
+fn input_section() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut section = mpv.input_section("app", SectionMode::Force)?;
+    section.bind("ctrl+s", move |event| tx.send(event.clone()).unwrap())?;
+    assert!(section.bind("ctrl+s x", |_| ()).is_err());
+    section.enable(false)?;
+
+    mpv.command_argv("keydown", &["ctrl+s"])?;
+    mpv.command_argv("keyup", &["ctrl+s"])?;
+    assert_event_occurs!(ev_ctx, 3., Ok(Event::ClientMessage(_)));
+    assert_event_occurs!(ev_ctx, 3., Ok(Event::ClientMessage(_)));
+
+    let down = rx.try_recv().unwrap();
+    assert_eq!(down.key, "Ctrl+s");
+    assert_eq!(down.state, KeyState::Down);
+    assert!(!down.is_mouse);
+    assert_eq!(rx.try_recv().unwrap().state, KeyState::Up);
+
+    Ok(())
+}
+
+#[test]
+fn osd() -> Result<()> {
+    let mpv = Mpv::new()?;
+
+    mpv.show_text("${volume}", Some(Duration::from_secs(2)), Some(1))?;
+    mpv.show_text("hello", None, None)?;
+
+    let osd_duration = mpv.get_property::<i64>("osd-duration")?;
+    mpv.show_progress(Some(Duration::from_millis(2500)), None)?;
+    assert_eq!(osd_duration, mpv.get_property::<i64>("osd-duration")?);
+    mpv.set_property("osd-level", 1)?;
+    mpv.show_progress(Some(Duration::from_secs(5)), Some(2))?;
+    assert_eq!(osd_duration, mpv.get_property::<i64>("osd-duration")?);
+
+    let mut banner = mpv.create_osd_overlay("{\\an8}Buffering...", 0, 720, 1)?;
+    let timeline = mpv.create_osd_overlay("{\\an2}00:00", 1280, 720, 0)?;
+    assert_ne!(banner.id(), timeline.id());
+    banner.update("{\\an8}Ready")?;
+    banner.set_z(2)?;
+    banner.remove()?;
+    drop(timeline);
+
+    Ok(())
+}
+
+#[test]
+fn overlay() -> Result<()> {
+    let mpv = Mpv::new()?;
+
+    let mut logo = mpv.create_overlay(10, 10, 2, 2)?;
+    let widget = mpv.create_overlay(0, 0, 1, 1)?;
+    assert_ne!(logo.id(), widget.id());
+    let client = mpv.create_client("overlays")?;
+    let badge = client.create_overlay(0, 0, 1, 1)?;
+    assert!(badge.id() != logo.id() && badge.id() != widget.id());
+    assert!(mpv.create_overlay(0, 0, 0, 1).is_err());
+
+    let red = [0, 0, 255, 255].repeat(4);
+    logo.update(&red)?;
+    assert_eq!(logo.pixels(), &red[..]);
+    assert!(logo.update(&red[..4]).is_err());
+    logo.update_with(|pixels| pixels[..4].copy_from_slice(&[255, 0, 0, 255]))?;
+    assert_eq!(&logo.pixels()[..8], &[255, 0, 0, 255, 0, 0, 255, 255]);
+    logo.set_position(20, 30)?;
+    assert_eq!(logo.position(), (20, 30));
+
+    let id = widget.id();
+    widget.remove()?;
+    assert_eq!(mpv.create_overlay(0, 0, 1, 1)?.id(), id);
+
+    Ok(())
+}
+
+#[test]
+fn playlist_diff() {
+    let old = Playlist {
+        entries: vec![
+            PlaylistEntry {
+                id: 1,
+                filename: "a.mkv".to_owned(),
+                title: None,
+                current: false,
+                playing: false,
+            },
+            PlaylistEntry {
+                id: 2,
+                filename: "b.mkv".to_owned(),
+                title: None,
+                current: false,
+                playing: false,
+            },
+            PlaylistEntry {
+                id: 3,
+                filename: "c.mkv".to_owned(),
+                title: None,
+                current: false,
+                playing: false,
+            },
+        ],
+    };
+    let new = Playlist {
+        entries: vec![
+            old.entries[2].clone(),
+            PlaylistEntry {
+                current: true,
+                ..old.entries[0].clone()
+            },
+            PlaylistEntry {
+                id: 4,
+                filename: "d.mkv".to_owned(),
+                title: Some("Extras".to_owned()),
+                current: false,
+                playing: false,
+            },
+        ],
+    };
+
+    assert_eq!(
+        old.diff(&new),
+        vec![
+            PlaylistChange::Removed { index: 1, id: 2 },
+            PlaylistChange::Moved {
+                id: 3,
+                from: 1,
+                to: 0
+            },
+            PlaylistChange::Updated {
+                index: 1,
+                entry: new.entries[1].clone()
+            },
+            PlaylistChange::Inserted {
+                index: 2,
+                entry: new.entries[2].clone()
+            },
+        ]
+    );
+    assert!(new.diff(&new).is_empty());
+}
+
+#[test]
+fn playlist() -> Result<()> {
+    let mpv = Mpv::new()?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+    let mut watcher = mpv.watch_playlist()?;
+
+    let path = "test-data/speech_12kbps_mb.wav";
+    let first = mpv.playlist_load_file(path, FileState::Append, None)?;
+    let second = mpv.playlist_load_file(path, FileState::Append, None)?;
+    let playlist = mpv.playlist()?;
+    assert_eq!(playlist.entries.len(), 2);
+    assert_eq!(playlist.entries[0].id, first);
+    assert_eq!(playlist.entries[0].filename, path);
+    assert!(playlist.playing().is_none());
+
+    mpv.playlist_move_id(first, None)?;
+    assert_eq!(mpv.playlist()?.position(first), Some(1));
+    mpv.playlist_remove_id(second)?;
+    assert_eq!(mpv.playlist()?.entries.len(), 1);
+
+    while ev_ctx.wait_event(0.5).is_some() {}
+    let changes = watcher.try_recv().unwrap()?;
+    assert_eq!(changes.len(), 1);
+    assert!(matches!(
+        changes[0],
+        PlaylistChange::Inserted { index: 0, .. }
+    ));
+    assert_eq!(watcher.current().entries[0].id, first);
+
+    Ok(())
+}
+
+#[test]
+fn tracks_diff() {
+    let old = Tracks {
+        tracks: vec![
+            Track {
+                id: 1,
+                track_type: TrackType::Audio,
+                title: None,
+                lang: Some("eng".to_owned()),
+                codec: Some("aac".to_owned()),
+                default: true,
+                forced: false,
+                external: false,
+                external_filename: None,
+                selected: true,
+            },
+            Track {
+                id: 1,
+                track_type: TrackType::Sub,
+                title: None,
+                lang: Some("ger".to_owned()),
+                codec: Some("subrip".to_owned()),
+                default: false,
+                forced: false,
+                external: true,
+                external_filename: Some("movie.srt".to_owned()),
+                selected: false,
+            },
+        ],
+    };
+    let new = Tracks {
+        tracks: vec![
+            Track {
+                title: Some("Commentary".to_owned()),
+                selected: false,
+                ..old.tracks[0].clone()
+            },
+            Track {
+                id: 1,
+                track_type: TrackType::Video,
+                title: None,
+                lang: None,
+                codec: Some("h264".to_owned()),
+                default: true,
+                forced: false,
+                external: false,
+                external_filename: None,
+                selected: true,
+            },
+        ],
+    };
+
+    assert_eq!(old.selected(TrackType::Audio), Some(&old.tracks[0]));
+    assert_eq!(old.of_type(TrackType::Video).count(), 0);
+    assert_eq!(
+        old.diff(&new),
+        vec![
+            TrackChange::Removed {
+                track_type: TrackType::Sub,
+                id: 1
+            },
+            TrackChange::Updated(new.tracks[0].clone()),
+            TrackChange::Added(new.tracks[1].clone()),
+            TrackChange::Selected {
+                track_type: TrackType::Video,
+                id: Some(1)
+            },
+            TrackChange::Selected {
+                track_type: TrackType::Audio,
+                id: None
+            },
+        ]
+    );
+}
+
+#[test]
+fn tracks() -> Result<()> {
+    let mpv = Mpv::with_initializer(|init| {
+        init.set_property("vo", "null")?;
+        init.set_property("ao", "null")?;
+        init.set_property("pause", true)?;
+        Ok(())
+    })?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+    let mut watcher = mpv.watch_tracks()?;
+
+    mpv.playlist_load_files(&[(
+        "test-data/speech_12kbps_mb.wav",
+        FileState::AppendPlay,
+        None,
+    )])?;
+    assert_event_occurs!(ev_ctx, 10., Ok(Event::FileLoaded));
+
+    let tracks = mpv.tracks()?;
+    let audio = tracks.selected(TrackType::Audio).unwrap();
+    assert!(!audio.external);
+    assert!(tracks.selected(TrackType::Video).is_none());
+
+    mpv.deselect_track(TrackType::Audio)?;
+    assert!(mpv.tracks()?.selected(TrackType::Audio).is_none());
+    mpv.select_track(TrackType::Audio, audio.id)?;
+
+    while ev_ctx.wait_event(0.5).is_some() {}
+    let changes = watcher.try_recv().unwrap()?;
+    assert!(changes.contains(&TrackChange::Added(watcher.current().tracks[0].clone())));
+
+    Ok(())
+}
+
+#[test]
+fn chapters() -> Result<()> {
+    let mpv = Mpv::with_initializer(|init| {
+        init.set_property("vo", "null")?;
+        init.set_property("ao", "null")?;
+        init.set_property("pause", true)?;
+        Ok(())
+    })?;
+    let mut ev_ctx = mpv.create_event_context();
+    ev_ctx.disable_deprecated_events()?;
+
+    mpv.playlist_load_files(&[(
+        "test-data/speech_12kbps_mb.wav",
+        FileState::AppendPlay,
+        None,
+    )])?;
+    assert_event_occurs!(ev_ctx, 10., Ok(Event::FileLoaded));
+    assert!(mpv.chapters()?.is_empty());
+    assert!(mpv.editions()?.len() <= 1);
+    let chapter = mpv.watch_chapter()?;
+
+    let markers = vec![
+        Chapter {
+            title: Some("Intro".to_owned()),
+            time: 0.,
+        },
+        Chapter {
+            title: None,
+            time: 1.,
+        },
+    ];
+    mpv.set_chapters(&markers)?;
+    assert_eq!(mpv.chapters()?.len(), 2);
+    assert_eq!(mpv.chapters()?[0], markers[0]);
+    assert_eq!(mpv.current_chapter()?, Some(0));
+
+    mpv.seek_chapter(1)?;
+    assert_event_occurs!(ev_ctx, 10., Ok(Event::PlaybackRestart));
+    assert_eq!(mpv.current_chapter()?, Some(1));
+    while ev_ctx.wait_event(0.5).is_some() {}
+    assert_eq!(chapter.try_recv(), Some(Ok(Some(1))));
+
+    Ok(())
+}